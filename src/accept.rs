@@ -0,0 +1,28 @@
+//! Presets for the `Accept` request header.
+
+/// A preset value for the `Accept` header, applied when a request doesn't set one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptPreset {
+    /// The accept string a desktop browser sends for page navigations.
+    Html,
+    /// JSON APIs.
+    Json,
+    /// Any content type (`*/*`).
+    Any,
+    /// A caller-supplied accept string.
+    Custom(String),
+}
+
+impl AcceptPreset {
+    /// Returns the header value for this preset.
+    pub fn as_str(&self) -> &str {
+        match self {
+            AcceptPreset::Html => {
+                "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"
+            }
+            AcceptPreset::Json => "application/json, text/plain;q=0.9, */*;q=0.8",
+            AcceptPreset::Any => "*/*",
+            AcceptPreset::Custom(value) => value,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Convenience constructors for request `Body` values.
+//!
+//! `Body` is defined in `spider-util`, so these are provided through the
+//! `BodyExt` extension trait; with it in scope they are called as
+//! `Body::form_from(&value)`.
+
+use serde::Serialize;
+use spider_util::error::SpiderError;
+use spider_util::request::Body;
+
+/// Extension constructors for `Body`.
+pub trait BodyExt: Sized {
+    /// Builds a form body from any value `serde_urlencoded` can serialize.
+    ///
+    /// This mirrors `Body::Json` for form posts: structs, maps and sequences of
+    /// pairs all work. Since the form body is a map, repeated keys keep only
+    /// their last value and field order isn't preserved.
+    fn form_from<T: Serialize + ?Sized>(value: &T) -> Result<Self, SpiderError>;
+}
+
+impl BodyExt for Body {
+    fn form_from<T: Serialize + ?Sized>(value: &T) -> Result<Self, SpiderError> {
+        let encoded = serde_urlencoded::to_string(value)
+            .map_err(|e| SpiderError::GeneralError(format!("Failed to encode form body: {}", e)))?;
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(&encoded)
+            .map_err(|e| SpiderError::GeneralError(format!("Failed to encode form body: {}", e)))?;
+        Ok(Body::Form(pairs.into_iter().collect()))
+    }
+}
@@ -0,0 +1,397 @@
+//! In-memory response caching for any `Downloader`.
+//!
+//! `CachingDownloader` wraps another downloader and answers repeated `GET` and
+//! `HEAD` requests from memory. Entries are keyed by request fingerprint and,
+//! following the response's `Vary` header, by the values of the request headers
+//! it names, so a response negotiated for one `Accept-Language` or
+//! `Accept-Encoding` is never served to a request asking for another. A
+//! response with `Vary: *` is never cached.
+//!
+//! Requests are fingerprinted the way the wrapped downloader sends them (see
+//! `Downloader::fingerprint`), so `with_query` parameters and normalization are
+//! part of the key. What counts as the same request can be changed with
+//! `CachingDownloader::fingerprint_fn`, e.g. to ignore a timestamp header.
+//!
+//! Entries are dropped once they outlive the TTL: on every store, expired
+//! variants under the stored key are removed, and at most once per TTL the
+//! whole cache is swept, so URLs that are never requested again don't stay in
+//! memory.
+//!
+//! Response `Cache-Control` is honored: `no-store` responses aren't cached,
+//! `no-cache` ones are revalidated on every use, and `max-age` shortens the
+//! freshness lifetime below the cache's TTL. A request can also set `cache` in
+//! its meta:
+//!
+//! - `"no-cache"`: bypass the cache entirely, neither reading nor storing
+//! - `"refresh"`: revalidate with the origin even if the cached copy is fresh
+//!
+//! Stale or refreshed entries carrying an `ETag` or `Last-Modified` are
+//! revalidated with a conditional request; a `304 Not Modified` answer serves
+//! the cached body again.
+
+use crate::Downloader;
+use crate::clock::{Clock, SystemClock};
+use crate::meta::{META_CACHE, META_FINGERPRINT};
+use crate::response_ext::ResponseExt;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::header::{CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, VARY};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use log::debug;
+use serde_json::Value;
+use spider_util::error::SpiderError;
+use spider_util::request::Request;
+use spider_util::response::Response;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use url::Url;
+
+/// Computes the key identifying equivalent requests.
+pub type FingerprintFn = Arc<dyn Fn(&Request) -> String + Send + Sync>;
+
+/// A cached response and the request header values it was negotiated for.
+#[derive(Clone)]
+struct CacheEntry {
+    url: Url,
+    request_url: Url,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    /// Request header values named by the response's `Vary` header.
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    stored_at: Instant,
+    /// How long the entry may be served without revalidation.
+    fresh_for: Duration,
+}
+
+impl CacheEntry {
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_headers.get(name) == value.as_ref())
+    }
+
+    fn is_fresh(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.stored_at) < self.fresh_for
+    }
+
+    fn is_expired(&self, now: Instant, ttl: Duration) -> bool {
+        now.saturating_duration_since(self.stored_at) >= ttl
+    }
+
+    /// Builds a cached response carrying the meta of `request`.
+    fn to_response(&self, request: &Request) -> Response {
+        let mut response = Response {
+            url: self.url.clone(),
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            request_url: self.request_url.clone(),
+            meta: request.meta.clone(),
+            cached: false,
+        };
+        response.mark_cached();
+        response
+    }
+
+    /// Turns a `304 Not Modified` answer into the cached response, keeping its meta.
+    fn revalidated(&self, mut response: Response) -> Response {
+        response.url = self.url.clone();
+        response.status = self.status;
+        response.headers = self.headers.clone();
+        response.body = self.body.clone();
+        response.request_url = self.request_url.clone();
+        response.mark_cached();
+        response
+    }
+}
+
+/// The `Cache-Control` directives relevant to a client cache.
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<Duration>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = CacheControl::default();
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for directive in value.split(',').map(str::trim) {
+                let (name, arg) = match directive.split_once('=') {
+                    Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+                    None => (directive, None),
+                };
+                match name.to_ascii_lowercase().as_str() {
+                    "no-store" => directives.no_store = true,
+                    "no-cache" => directives.no_cache = true,
+                    "max-age" => {
+                        directives.max_age = arg
+                            .and_then(|arg| arg.parse::<u64>().ok())
+                            .map(Duration::from_secs)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        directives
+    }
+}
+
+/// Header names listed in `Vary`, or `None` for `Vary: *`.
+fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(VARY) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            if name == "*" {
+                return None;
+            }
+            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                names.push(name);
+            }
+        }
+    }
+    Some(names)
+}
+
+/// A `Downloader` that caches successful `GET`/`HEAD` responses of another downloader.
+pub struct CachingDownloader<D: Downloader> {
+    inner: D,
+    ttl: Duration,
+    /// `None` keys on the wrapped downloader's `fingerprint`
+    fingerprint_fn: Option<FingerprintFn>,
+    entries: RwLock<HashMap<String, Vec<CacheEntry>>>,
+    /// When expired entries were last swept from every key
+    swept_at: Mutex<Option<Instant>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<D: Downloader> CachingDownloader<D> {
+    /// Wraps `inner`, keeping responses for `ttl`.
+    pub fn new(inner: D, ttl: Duration) -> Self {
+        CachingDownloader {
+            inner,
+            ttl,
+            fingerprint_fn: None,
+            entries: RwLock::new(HashMap::new()),
+            swept_at: Mutex::new(None),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Keys the cache with `fingerprint_fn` instead of the wrapped downloader's `fingerprint`.
+    ///
+    /// `fingerprint_fn` sees the request as built, before the downloader
+    /// rewrites it.
+    pub fn fingerprint_fn(mut self, fingerprint_fn: FingerprintFn) -> Self {
+        self.fingerprint_fn = Some(fingerprint_fn);
+        self
+    }
+
+    /// Replaces the clock entry ages are measured on.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the wrapped downloader.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Removes every cached response.
+    pub async fn clear(&self) {
+        self.entries.write().await.clear();
+    }
+
+    fn key(&self, request: &Request) -> String {
+        match &self.fingerprint_fn {
+            Some(fingerprint_fn) => fingerprint_fn(request),
+            None => self.inner.fingerprint(request),
+        }
+    }
+
+    /// Returns the entry negotiated for `request_headers`, fresh or not, unless it outlived the TTL.
+    async fn lookup(&self, key: &str, request_headers: &HeaderMap) -> Option<CacheEntry> {
+        let now = self.clock.now();
+        let entries = self.entries.read().await;
+        entries
+            .get(key)?
+            .iter()
+            .find(|entry| !entry.is_expired(now, self.ttl) && entry.matches(request_headers))
+            .cloned()
+    }
+
+    async fn store(&self, key: String, request_headers: &HeaderMap, response: &Response) {
+        let Some(names) = vary_names(&response.headers) else {
+            debug!("Not caching {}: Vary: *", response.url);
+            return;
+        };
+        let cache_control = CacheControl::parse(&response.headers);
+        if cache_control.no_store {
+            debug!("Not caching {}: Cache-Control: no-store", response.url);
+            return;
+        }
+        let fresh_for = if cache_control.no_cache {
+            Duration::ZERO
+        } else {
+            cache_control
+                .max_age
+                .map_or(self.ttl, |age| age.min(self.ttl))
+        };
+        let vary: Vec<(HeaderName, Option<HeaderValue>)> = names
+            .into_iter()
+            .map(|name| {
+                let value = request_headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        self.insert(
+            key,
+            CacheEntry {
+                url: response.url.clone(),
+                request_url: response.request_url.clone(),
+                status: response.status,
+                headers: response.headers.clone(),
+                body: response.body.clone(),
+                vary,
+                stored_at: self.clock.now(),
+                fresh_for,
+            },
+        )
+        .await;
+    }
+
+    async fn insert(&self, key: String, entry: CacheEntry) {
+        let now = self.clock.now();
+        let mut entries = self.entries.write().await;
+        if self.sweep_due(now) {
+            let before = entries.len();
+            entries.retain(|_, variants| {
+                variants.retain(|existing| !existing.is_expired(now, self.ttl));
+                !variants.is_empty()
+            });
+            debug!(
+                "Swept {} expired cache keys",
+                before.saturating_sub(entries.len())
+            );
+        }
+        let variants = entries.entry(key).or_default();
+        // Replace the variant negotiated for the same header values, and drop expired ones.
+        variants
+            .retain(|existing| !existing.is_expired(now, self.ttl) && existing.vary != entry.vary);
+        variants.push(entry);
+    }
+
+    /// Returns whether a TTL has passed since the last full sweep, and if so restarts the wait.
+    fn sweep_due(&self, now: Instant) -> bool {
+        let mut swept_at = self.swept_at.lock().unwrap_or_else(|e| e.into_inner());
+        match *swept_at {
+            Some(at) if now.saturating_duration_since(at) < self.ttl => false,
+            Some(_) => {
+                *swept_at = Some(now);
+                true
+            }
+            None => {
+                // Nothing stored before the first insert can have expired yet.
+                *swept_at = Some(now);
+                false
+            }
+        }
+    }
+}
+
+/// Adds `If-None-Match` / `If-Modified-Since` from a cached entry; returns whether any was added.
+fn add_validators(request: &mut Request, entry: &CacheEntry) -> bool {
+    if request.headers.contains_key(IF_NONE_MATCH)
+        || request.headers.contains_key(IF_MODIFIED_SINCE)
+    {
+        // The caller is revalidating on its own; let its 304 through.
+        return false;
+    }
+    let mut added = false;
+    if let Some(etag) = entry.headers.get(ETAG) {
+        request.headers.insert(IF_NONE_MATCH, etag.clone());
+        added = true;
+    }
+    if let Some(last_modified) = entry.headers.get(LAST_MODIFIED) {
+        request
+            .headers
+            .insert(IF_MODIFIED_SINCE, last_modified.clone());
+        added = true;
+    }
+    added
+}
+
+#[async_trait]
+impl<D: Downloader> Downloader for CachingDownloader<D> {
+    type Client = D::Client;
+
+    async fn download(&self, mut request: Request) -> Result<Response, SpiderError> {
+        let cacheable = request.method == Method::GET || request.method == Method::HEAD;
+        let mode = request
+            .meta
+            .get(META_CACHE)
+            .and_then(|v| v.as_str().map(str::to_string));
+        if !cacheable || mode.as_deref() == Some("no-cache") {
+            return self.inner.download(request).await;
+        }
+        let refresh = mode.as_deref() == Some("refresh");
+
+        let key = self.key(&request);
+        let request_headers = request.headers.clone();
+        let cached = self.lookup(&key, &request_headers).await;
+        let mut revalidating = None;
+        if let Some(entry) = cached {
+            if entry.is_fresh(self.clock.now()) && !refresh {
+                debug!("Serving {} from cache", request.url);
+                let response = entry.to_response(&request);
+                response
+                    .meta
+                    .insert(META_FINGERPRINT.into(), Value::String(key));
+                return Ok(response);
+            }
+            if add_validators(&mut request, &entry) {
+                debug!("Revalidating cached {}", request.url);
+                revalidating = Some(entry);
+            }
+        }
+
+        let response = self.inner.download(request).await?;
+        if response.status == StatusCode::NOT_MODIFIED
+            && let Some(mut entry) = revalidating
+        {
+            debug!("{} not modified; serving cached copy", response.url);
+            entry.stored_at = self.clock.now();
+            let response = entry.revalidated(response);
+            self.insert(key, entry).await;
+            return Ok(response);
+        }
+        if response.status.is_success() {
+            self.store(key, &request_headers, &response).await;
+        }
+        Ok(response)
+    }
+
+    fn client(&self) -> &Self::Client {
+        self.inner.client()
+    }
+
+    /// Fingerprints like the wrapped downloader.
+    fn fingerprint(&self, request: &Request) -> String {
+        self.inner.fingerprint(request)
+    }
+
+    fn would_allow(&self, url: &Url) -> Result<(), SpiderError> {
+        self.inner.would_allow(url)
+    }
+}
@@ -0,0 +1,53 @@
+//! Time source used for every delay the downloader introduces.
+//!
+//! Retry backoff and other waits go through a `Clock` instead of calling
+//! `tokio::time::sleep` directly, so timing logic can be tested without
+//! wall-clock waits.
+
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+/// A source of the current time and of delays.
+#[async_trait]
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Waits for `duration`.
+    async fn sleep(&self, duration: Duration);
+}
+
+/// The real clock: `std::time::Instant` for now, tokio timers for sleeping.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// A clock driven entirely by tokio's time, including `now()`.
+///
+/// Under `tokio::time::pause()` both `now()` and `sleep()` follow the paused
+/// virtual clock, so tests can advance time instantly with
+/// `tokio::time::advance` or rely on auto-advance.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
@@ -0,0 +1,168 @@
+//! Parsing of `Set-Cookie` response headers.
+//!
+//! The downloader keeps no cookie jar, but login flows often need the cookies a
+//! response set. `ResponseExt::cookies` parses every `Set-Cookie` header into a
+//! `Cookie` following RFC 6265 section 5.2. Headers without a `name=value` pair
+//! or with an empty name are skipped, and unknown or malformed attributes are
+//! ignored, so a single bad header never hides the others.
+
+use http::HeaderMap;
+use http::header::SET_COOKIE;
+use std::time::{Duration, SystemTime};
+
+/// A cookie set by a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// Cookie name.
+    pub name: String,
+    /// Cookie value, without surrounding quotes.
+    pub value: String,
+    /// `Domain` attribute, without a leading dot.
+    pub domain: Option<String>,
+    /// `Path` attribute.
+    pub path: Option<String>,
+    /// `Expires` attribute.
+    pub expires: Option<SystemTime>,
+    /// `Max-Age` attribute; zero or negative values become zero, deleting the cookie.
+    pub max_age: Option<Duration>,
+    /// `Secure` flag.
+    pub secure: bool,
+    /// `HttpOnly` flag.
+    pub http_only: bool,
+    /// `SameSite` attribute as sent, e.g. `Lax`.
+    pub same_site: Option<String>,
+}
+
+impl Cookie {
+    /// Parses one `Set-Cookie` header value, or returns `None` if it has no valid `name=value` pair.
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+        for attribute in parts {
+            let (key, arg) = match attribute.split_once('=') {
+                Some((key, arg)) => (key.trim(), arg.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "domain" if !arg.is_empty() => {
+                    cookie.domain = Some(arg.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" if arg.starts_with('/') => cookie.path = Some(arg.to_string()),
+                "expires" => {
+                    if let Ok(expires) = httpdate::parse_http_date(arg) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = arg.parse::<i64>() {
+                        cookie.max_age = Some(Duration::from_secs(seconds.max(0) as u64));
+                    }
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" if !arg.is_empty() => cookie.same_site = Some(arg.to_string()),
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+}
+
+/// Parses every `Set-Cookie` header in `headers`, skipping malformed ones.
+pub(crate) fn parse_set_cookies(headers: &HeaderMap) -> Vec<Cookie> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .filter_map(Cookie::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn domain_loses_its_leading_dot_and_case() {
+        let cookie = Cookie::parse("id=1; Domain=.Example.COM").unwrap();
+        assert_eq!(cookie.domain.as_deref(), Some("example.com"));
+
+        let cookie = Cookie::parse("id=1; Domain=").unwrap();
+        assert_eq!(cookie.domain, None);
+    }
+
+    #[test]
+    fn path_must_be_absolute() {
+        let cookie = Cookie::parse("id=1; Path=/account").unwrap();
+        assert_eq!(cookie.path.as_deref(), Some("/account"));
+
+        let cookie = Cookie::parse("id=1; Path=account").unwrap();
+        assert_eq!(cookie.path, None);
+    }
+
+    #[test]
+    fn expires_and_max_age() {
+        let cookie =
+            Cookie::parse("id=1; Expires=Sun, 06 Nov 1994 08:49:37 GMT; Max-Age=3600").unwrap();
+        assert_eq!(
+            cookie.expires,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(cookie.max_age, Some(Duration::from_secs(3600)));
+
+        let cookie = Cookie::parse("id=1; Max-Age=-5").unwrap();
+        assert_eq!(cookie.max_age, Some(Duration::ZERO));
+
+        let cookie = Cookie::parse("id=1; Expires=soon; Max-Age=abc").unwrap();
+        assert_eq!(cookie.expires, None);
+        assert_eq!(cookie.max_age, None);
+    }
+
+    #[test]
+    fn flags_and_same_site() {
+        let cookie = Cookie::parse("id=\"abc\"; secure; HTTPONLY; SameSite=Lax").unwrap();
+        assert_eq!(cookie.value, "abc");
+        assert!(cookie.secure);
+        assert!(cookie.http_only);
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+
+        let cookie = Cookie::parse("id=abc").unwrap();
+        assert!(!cookie.secure);
+        assert!(!cookie.http_only);
+    }
+
+    #[test]
+    fn malformed_headers_are_skipped() {
+        let mut headers = HeaderMap::new();
+        for value in ["no-pair", "=nameless", "ok=1; Secure"] {
+            headers.append(SET_COOKIE, HeaderValue::from_static(value));
+        }
+
+        let cookies = parse_set_cookies(&headers);
+
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "ok");
+        assert!(cookies[0].secure);
+    }
+}
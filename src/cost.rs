@@ -0,0 +1,34 @@
+//! Advisory cost hints for scheduling.
+//!
+//! Schedulers that budget proxy traffic or bandwidth can ask a downloader how
+//! expensive a request is likely to be through `Downloader::estimated_cost`.
+//! The estimate is a hint only; downloaders never enforce it.
+
+/// The expected cost of downloading a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestCost {
+    /// Relative cost, where 1.0 is an ordinary direct request.
+    pub weight: f64,
+    /// Whether the request would be sent through a proxy.
+    pub uses_proxy: bool,
+    /// Expected response body size in bytes, when the downloader has an estimate.
+    pub expected_bytes: Option<u64>,
+    /// Expected probability, from 0.0 to 1.0, that the request fails and is retried or given up.
+    pub failure_rate: f64,
+}
+
+impl RequestCost {
+    /// The cost of an ordinary direct request with nothing else known about it.
+    pub const NEUTRAL: RequestCost = RequestCost {
+        weight: 1.0,
+        uses_proxy: false,
+        expected_bytes: None,
+        failure_rate: 0.0,
+    };
+}
+
+impl Default for RequestCost {
+    fn default() -> Self {
+        Self::NEUTRAL
+    }
+}
@@ -0,0 +1,215 @@
+//! Incremental decoding of `Content-Encoding` compressed bodies.
+//!
+//! Bodies are fed to a `BodyDecoder` chunk by chunk as they arrive, so the
+//! decoded size is known at every step rather than only after the whole body
+//! has been inflated.
+//!
+//! `gzip` and `deflate` are always supported; `br` and `zstd` need the
+//! `brotli` and `zstd` features.
+
+#[cfg(feature = "reqwest")]
+use crate::error::DownloadError;
+use flate2::write::{GzDecoder, ZlibDecoder};
+#[cfg(feature = "reqwest")]
+use http::header::{CONTENT_ENCODING, TRANSFER_ENCODING};
+#[cfg(feature = "reqwest")]
+use http::{HeaderMap, HeaderName};
+use std::io::{self, Write};
+
+/// Size of the slices inner decoders are fed, so their output is checked
+/// against the limit as it grows rather than once fully inflated.
+#[cfg(feature = "reqwest")]
+const INNER_SLICE_LEN: usize = 8 * 1024;
+
+/// Returns the codings applied to a body, in the order they must be removed.
+///
+/// A sender applies content codings first and transfer codings on top, each
+/// header listing codings in the order they were applied. Decoding therefore
+/// undoes `Transfer-Encoding` (last listed first) and then `Content-Encoding`
+/// (last listed first). `chunked` and `identity` are skipped since the HTTP
+/// stack already handles them.
+#[cfg(feature = "reqwest")]
+pub(crate) fn codings_to_decode(headers: &HeaderMap) -> Vec<String> {
+    let listed = |name: HeaderName| -> Vec<String> {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|coding| coding.trim().to_ascii_lowercase())
+            .filter(|coding| !coding.is_empty() && coding != "chunked" && coding != "identity")
+            .collect()
+    };
+    let mut codings: Vec<String> = listed(TRANSFER_ENCODING).into_iter().rev().collect();
+    codings.extend(listed(CONTENT_ENCODING).into_iter().rev());
+    codings
+}
+
+/// Returns the codings this build can decode, in order of preference for `Accept-Encoding`.
+#[cfg(feature = "reqwest")]
+pub(crate) fn supported_encodings() -> Vec<&'static str> {
+    let mut encodings = Vec::new();
+    #[cfg(feature = "zstd")]
+    encodings.push("zstd");
+    #[cfg(feature = "brotli")]
+    encodings.push("br");
+    encodings.extend(["gzip", "deflate"]);
+    encodings
+}
+
+/// A streaming decoder for one supported content coding.
+pub(crate) enum BodyDecoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    #[cfg(feature = "brotli")]
+    Brotli(Box<brotli::DecompressorWriter<Vec<u8>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl BodyDecoder {
+    /// Returns a decoder for `encoding`, or `None` if it isn't a supported coding.
+    pub(crate) fn for_encoding(encoding: &str) -> Option<Self> {
+        match encoding.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(BodyDecoder::Gzip(GzDecoder::new(Vec::new()))),
+            "deflate" => Some(BodyDecoder::Deflate(ZlibDecoder::new(Vec::new()))),
+            #[cfg(feature = "brotli")]
+            "br" => Some(BodyDecoder::Brotli(Box::new(
+                brotli::DecompressorWriter::new(Vec::new(), 4096),
+            ))),
+            #[cfg(feature = "zstd")]
+            "zstd" => zstd::stream::write::Decoder::new(Vec::new())
+                .ok()
+                .map(BodyDecoder::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Feeds one chunk of encoded bytes.
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        match self {
+            BodyDecoder::Gzip(decoder) => decoder.write_all(chunk),
+            BodyDecoder::Deflate(decoder) => decoder.write_all(chunk),
+            #[cfg(feature = "brotli")]
+            BodyDecoder::Brotli(decoder) => decoder.write_all(chunk),
+            #[cfg(feature = "zstd")]
+            BodyDecoder::Zstd(decoder) => decoder.write_all(chunk),
+        }
+    }
+
+    /// Returns the number of decoded bytes produced so far.
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn decoded_len(&self) -> usize {
+        match self {
+            BodyDecoder::Gzip(decoder) => decoder.get_ref().len(),
+            BodyDecoder::Deflate(decoder) => decoder.get_ref().len(),
+            #[cfg(feature = "brotli")]
+            BodyDecoder::Brotli(decoder) => decoder.get_ref().len(),
+            #[cfg(feature = "zstd")]
+            BodyDecoder::Zstd(decoder) => decoder.get_ref().len(),
+        }
+    }
+
+    /// Finishes decoding and returns the decoded body.
+    pub(crate) fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            BodyDecoder::Gzip(decoder) => decoder.finish(),
+            BodyDecoder::Deflate(decoder) => decoder.finish(),
+            #[cfg(feature = "brotli")]
+            BodyDecoder::Brotli(mut decoder) => {
+                decoder.close()?;
+                decoder.into_inner().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "incomplete brotli stream")
+                })
+            }
+            #[cfg(feature = "zstd")]
+            BodyDecoder::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(decoder.into_inner())
+            }
+        }
+    }
+}
+
+/// Undoes a list of codings on a body arriving in chunks, bounding the decoded size.
+#[cfg(feature = "reqwest")]
+pub(crate) struct DecoderChain {
+    outer: BodyDecoder,
+    inner: Vec<BodyDecoder>,
+    encoding: String,
+    limit: Option<usize>,
+}
+
+#[cfg(feature = "reqwest")]
+impl DecoderChain {
+    /// Returns a chain undoing `codings` in order, or `None` if there are none or one isn't supported.
+    ///
+    /// Decoding fails with `DownloadError::DecompressionBomb` once the output
+    /// exceeds `limit` bytes.
+    pub(crate) fn new(codings: &[String], limit: Option<usize>) -> Option<Self> {
+        let mut decoders = codings
+            .iter()
+            .map(|coding| BodyDecoder::for_encoding(coding))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter();
+        let outer = decoders.next()?;
+        Some(DecoderChain {
+            outer,
+            inner: decoders.collect(),
+            encoding: codings.join(", "),
+            limit,
+        })
+    }
+
+    /// Feeds one chunk of encoded bytes.
+    ///
+    /// The outermost coding is undone as the chunks arrive, so an oversized
+    /// body is abandoned mid-stream.
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> Result<(), DownloadError> {
+        if let Err(e) = self.outer.write(chunk) {
+            return Err(decompression_error(&self.encoding, e));
+        }
+        check_limit(self.outer.decoded_len(), self.limit)
+    }
+
+    /// Finishes decoding and returns the decoded body.
+    ///
+    /// Inner codings are undone in turn on the already bounded output of the
+    /// outer one, fed in slices so a nested bomb is stopped as it inflates.
+    pub(crate) fn finish(self) -> Result<Vec<u8>, DownloadError> {
+        let DecoderChain {
+            outer,
+            inner,
+            encoding,
+            limit,
+        } = self;
+        let error = |e| decompression_error(&encoding, e);
+        let mut decoded = outer.finish().map_err(error)?;
+        check_limit(decoded.len(), limit)?;
+        for mut decoder in inner {
+            for slice in decoded.chunks(INNER_SLICE_LEN) {
+                decoder.write(slice).map_err(error)?;
+                check_limit(decoder.decoded_len(), limit)?;
+            }
+            decoded = decoder.finish().map_err(error)?;
+            check_limit(decoded.len(), limit)?;
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn check_limit(decoded: usize, limit: Option<usize>) -> Result<(), DownloadError> {
+    match limit {
+        Some(limit) if decoded > limit => Err(DownloadError::DecompressionBomb { limit }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn decompression_error(encoding: &str, e: io::Error) -> DownloadError {
+    DownloadError::Decompression {
+        encoding: encoding.to_string(),
+        reason: e.to_string(),
+    }
+}
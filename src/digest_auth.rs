@@ -0,0 +1,297 @@
+//! HTTP Digest access authentication (RFC 7616).
+//!
+//! reqwest only handles Basic credentials. With Digest credentials configured,
+//! a `401` carrying a `WWW-Authenticate: Digest` challenge is answered by
+//! resending the request with a computed `Authorization` header. The last
+//! challenge of each origin is remembered, so later requests to it carry an
+//! answer up front with an increasing nonce count instead of paying for a
+//! `401` each time.
+//!
+//! `MD5`, `SHA-256` and their `-sess` variants are supported with `qop=auth`
+//! or without `qop`. Challenges offering only `qop=auth-int` are not answered.
+
+use crate::hash::to_hex;
+use http::header::WWW_AUTHENTICATE;
+use http::{HeaderMap, HeaderValue, Method};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use url::Url;
+use uuid::Uuid;
+
+/// Hash algorithm named in a challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Algorithm::Md5),
+            "MD5-SESS" => Some(Algorithm::Md5Sess),
+            "SHA-256" => Some(Algorithm::Sha256),
+            "SHA-256-SESS" => Some(Algorithm::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Md5Sess => "MD5-sess",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn is_sess(self) -> bool {
+        matches!(self, Algorithm::Md5Sess | Algorithm::Sha256Sess)
+    }
+
+    fn hash(self, data: &str) -> String {
+        match self {
+            Algorithm::Md5 | Algorithm::Md5Sess => to_hex(&Md5::digest(data.as_bytes())),
+            Algorithm::Sha256 | Algorithm::Sha256Sess => to_hex(&Sha256::digest(data.as_bytes())),
+        }
+    }
+}
+
+/// A `Digest` challenge from `WWW-Authenticate`.
+#[derive(Debug, Clone)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    /// Whether the server asked for `qop=auth`; without it the RFC 2069 form is used.
+    qop_auth: bool,
+}
+
+impl Challenge {
+    /// Parses the parameters following `Digest`, or returns `None` if they can't be answered.
+    fn parse(params: &[(String, String)]) -> Option<Self> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let algorithm = match param("algorithm") {
+            Some(name) => Algorithm::parse(&name)?,
+            None => Algorithm::Md5,
+        };
+        let qop_auth = match param("qop") {
+            Some(qop) => {
+                if !qop
+                    .split(',')
+                    .any(|q| q.trim().eq_ignore_ascii_case("auth"))
+                {
+                    return None;
+                }
+                true
+            }
+            None => false,
+        };
+        Some(Challenge {
+            realm: param("realm").unwrap_or_default(),
+            nonce: param("nonce")?,
+            opaque: param("opaque"),
+            algorithm,
+            qop_auth,
+        })
+    }
+}
+
+/// Digest credentials and the last challenge of each origin.
+pub(crate) struct DigestAuth {
+    username: String,
+    password: String,
+    /// Last challenge per origin, with the nonce count sent for it so far
+    challenges: Mutex<HashMap<String, (Challenge, u32)>>,
+}
+
+impl DigestAuth {
+    pub(crate) fn new(username: &str, password: &str) -> Self {
+        DigestAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Answers the challenge last received from `url`'s origin, if any.
+    pub(crate) fn authorization(&self, method: &Method, url: &Url) -> Option<HeaderValue> {
+        let mut challenges = self.challenges.lock().unwrap_or_else(|e| e.into_inner());
+        let (challenge, nonce_count) = challenges.get_mut(&origin(url))?;
+        *nonce_count += 1;
+        self.answer(challenge, *nonce_count, method, url)
+    }
+
+    /// Remembers the `Digest` challenge of a `401` response and answers it.
+    ///
+    /// Returns `None` when the response carries no challenge that can be answered.
+    pub(crate) fn respond(
+        &self,
+        headers: &HeaderMap,
+        method: &Method,
+        url: &Url,
+    ) -> Option<HeaderValue> {
+        let challenges: Vec<Challenge> = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(digest_params)
+            .filter_map(|params| Challenge::parse(&params))
+            .collect();
+        // RFC 7616 asks clients to prefer the strongest algorithm offered.
+        let challenge = challenges
+            .iter()
+            .find(|c| matches!(c.algorithm, Algorithm::Sha256 | Algorithm::Sha256Sess))
+            .or(challenges.first())?
+            .clone();
+        let header = self.answer(&challenge, 1, method, url);
+        let mut cached = self.challenges.lock().unwrap_or_else(|e| e.into_inner());
+        cached.insert(origin(url), (challenge, 1));
+        header
+    }
+
+    /// Computes the `Authorization` header for `challenge` with nonce count `nonce_count`.
+    fn answer(
+        &self,
+        challenge: &Challenge,
+        nonce_count: u32,
+        method: &Method,
+        url: &Url,
+    ) -> Option<HeaderValue> {
+        let algorithm = challenge.algorithm;
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let cnonce = Uuid::new_v4().simple().to_string();
+        let nc = format!("{:08x}", nonce_count);
+
+        let mut ha1 = algorithm.hash(&format!(
+            "{}:{}:{}",
+            self.username, challenge.realm, self.password
+        ));
+        if algorithm.is_sess() {
+            ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+        }
+        let ha2 = algorithm.hash(&format!("{}:{}", method.as_str(), uri));
+        let response = if challenge.qop_auth {
+            algorithm.hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, challenge.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            algorithm.hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote(&self.username),
+            quote(&challenge.realm),
+            quote(&challenge.nonce),
+            quote(&uri),
+            algorithm.name(),
+            response
+        );
+        if let Some(opaque) = &challenge.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+        }
+        if challenge.qop_auth {
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+        }
+        let mut value = HeaderValue::from_str(&header).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+}
+
+/// The origin challenges are remembered for, e.g. `https://example.com:443`.
+fn origin(url: &Url) -> String {
+    format!(
+        "{}://{}:{}",
+        url.scheme(),
+        url.host_str().unwrap_or(""),
+        url.port_or_known_default().unwrap_or(0)
+    )
+}
+
+/// Escapes a value for a quoted string.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Returns the parameters of each `Digest` challenge in a `WWW-Authenticate` value.
+///
+/// A value may hold several challenges, e.g. `Basic realm="a", Digest
+/// realm="b", nonce="c"`; parameters run until the next scheme name.
+fn digest_params(value: &str) -> Vec<Vec<(String, String)>> {
+    let mut challenges = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            break;
+        }
+        let token_end = rest
+            .find(|c: char| c == '=' || c == ',' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let token = &rest[..token_end];
+        let after = rest[token_end..].trim_start();
+        if let Some(after) = after.strip_prefix('=') {
+            // A parameter of the current challenge.
+            let after = after.trim_start();
+            let (value, remaining) = match after.strip_prefix('"') {
+                Some(quoted) => read_quoted(quoted),
+                None => {
+                    let end = after.find(',').unwrap_or(after.len());
+                    (after[..end].trim().to_string(), &after[end..])
+                }
+            };
+            if let Some(params) = &mut current {
+                params.push((token.to_string(), value));
+            }
+            rest = remaining;
+        } else {
+            // A new scheme name.
+            if let Some(params) = current.take() {
+                challenges.push(params);
+            }
+            if token.eq_ignore_ascii_case("digest") {
+                current = Some(Vec::new());
+            }
+            rest = after;
+        }
+    }
+    if let Some(params) = current {
+        challenges.push(params);
+    }
+    challenges
+}
+
+/// Reads a quoted string whose opening quote was consumed, returning it and the remaining input.
+fn read_quoted(input: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            '"' => return (value, &input[i + 1..]),
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
@@ -0,0 +1,65 @@
+//! Address family selection for outgoing connections.
+//!
+//! Some origins misbehave over IPv6 on dual-stack hosts. `AddressFamily`
+//! restricts or orders the addresses a host name resolves to, through a
+//! resolver installed on the reqwest clients the downloader builds. Every mode
+//! is supported by the reqwest backend; when a proxy is used, the family
+//! applies to the connection to the proxy, which resolves the target itself.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::SocketAddr;
+
+/// Which IP address families connections may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressFamily {
+    /// Use addresses in the order the system resolver returns them.
+    #[default]
+    Any,
+    /// Only connect over IPv4.
+    V4Only,
+    /// Only connect over IPv6.
+    V6Only,
+    /// Try IPv4 addresses first, then IPv6.
+    PreferV4,
+    /// Try IPv6 addresses first, then IPv4.
+    PreferV6,
+}
+
+impl AddressFamily {
+    /// Filters and orders resolved addresses according to the family.
+    pub(crate) fn apply(self, addrs: impl Iterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = match self {
+            AddressFamily::V4Only => addrs.filter(SocketAddr::is_ipv4).collect(),
+            AddressFamily::V6Only => addrs.filter(SocketAddr::is_ipv6).collect(),
+            _ => addrs.collect(),
+        };
+        match self {
+            // Stable sorts keep the resolver's order within each family.
+            AddressFamily::PreferV4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+            AddressFamily::PreferV6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+            _ => {}
+        }
+        addrs
+    }
+}
+
+/// Resolves host names with the system resolver, then applies an `AddressFamily`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FamilyResolver {
+    pub(crate) family: AddressFamily,
+}
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.family;
+        Box::pin(async move {
+            // The port is replaced by the connector, so any value works here.
+            let resolved = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let addrs = family.apply(resolved);
+            if addrs.is_empty() {
+                return Err(format!("no {:?} address found for {}", family, name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,270 @@
+//! Error types specific to the downloader.
+//!
+//! `SpiderError` lives in `spider-util` and is shared by the whole framework, so
+//! conditions that only the downloader can produce are described by
+//! `DownloadError` and converted into `SpiderError` at the `Downloader` boundary.
+//! `SpiderError` can only carry them as a message; entry points that keep them
+//! structured, such as `ReqwestClientDownloader::try_download`, return a
+//! `DownloadFailure` instead.
+
+use spider_util::error::SpiderError;
+use std::fmt;
+use std::time::Duration;
+
+/// The broad category of a failed download attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptErrorKind {
+    /// The attempt timed out.
+    Timeout,
+    /// The connection could not be established.
+    Connect,
+    /// The server answered with a retryable status.
+    Status,
+    /// Reading the response body failed.
+    Body,
+    /// Any other failure.
+    Other,
+}
+
+/// A record of one failed download attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttemptError {
+    /// 1-based attempt number.
+    pub attempt: u32,
+    /// Delay waited before this attempt (zero for the first).
+    pub delay: Duration,
+    /// Category of the failure.
+    pub kind: AttemptErrorKind,
+    /// Error message or status line.
+    pub message: String,
+}
+
+impl fmt::Display for AttemptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attempt {} (after {:?}): {:?}: {}",
+            self.attempt, self.delay, self.kind, self.message
+        )
+    }
+}
+
+/// Errors raised by downloader components.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// No recorded response exists for the given request fingerprint.
+    NoRecording { fingerprint: String },
+    /// A recording exists on disk but could not be read or decoded.
+    InvalidRecording { path: String, reason: String },
+    /// Every attempt failed; the last entry is the final error.
+    RetriesExhausted { attempts: Vec<AttemptError> },
+    /// The body could not be decoded according to its content coding.
+    Decompression { encoding: String, reason: String },
+    /// The decoded body grew past the configured limit.
+    DecompressionBomb { limit: usize },
+    /// The body as received is larger than the configured limit.
+    BodyTooLarge { limit: usize },
+    /// The headers arrived but the body took longer than `timeout` to read.
+    BodyReadTimeout { url: String, timeout: Duration },
+    /// The body could not be deserialized; `snippet` holds its first bytes.
+    Decode { reason: String, snippet: String },
+    /// The body is not valid UTF-8; `offset` is where the first invalid sequence starts.
+    InvalidUtf8 { offset: usize },
+    /// A response validator rejected the response.
+    InvalidResponse { reason: String },
+    /// The server switched protocols (`101`), e.g. to WebSocket, which the downloader can't speak.
+    UnexpectedUpgrade { protocol: String },
+    /// The download was aborted through `abort_all`.
+    Cancelled,
+    /// The downloader's global deadline has passed.
+    DeadlineExceeded,
+    /// The server only offered TLS versions older than the allowed minimum.
+    TlsVersionTooLow { url: String, min_version: String },
+    /// The request was refused because `limit` connections were already open.
+    ResourceExhausted { limit: usize },
+    /// The URL can't be downloaded, e.g. because of its scheme.
+    UnsupportedUrl { url: String, reason: String },
+    /// A reachability probe failed at `stage`: `resolve`, `connect` or `tls`.
+    ProbeFailed {
+        url: String,
+        stage: String,
+        reason: String,
+    },
+    /// An OAuth2 token could not be obtained from `url`.
+    TokenRequestFailed { url: String, reason: String },
+    /// The body has another content type than the one asked for, e.g. an HTML
+    /// block page instead of JSON; `snippet` holds its first bytes.
+    UnexpectedContentType {
+        expected: String,
+        got: String,
+        snippet: String,
+    },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::NoRecording { fingerprint } => {
+                write!(f, "No recording found for fingerprint {}", fingerprint)
+            }
+            DownloadError::InvalidRecording { path, reason } => {
+                write!(f, "Invalid recording at {}: {}", path, reason)
+            }
+            DownloadError::RetriesExhausted { attempts } => {
+                write!(f, "All {} attempts failed", attempts.len())?;
+                if let Some(last) = attempts.last() {
+                    write!(f, "; last {}", last)?;
+                }
+                Ok(())
+            }
+            DownloadError::Decompression { encoding, reason } => {
+                write!(f, "Failed to decode {} body: {}", encoding, reason)
+            }
+            DownloadError::DecompressionBomb { limit } => {
+                write!(f, "Decompressed body exceeds the limit of {} bytes", limit)
+            }
+            DownloadError::BodyTooLarge { limit } => {
+                write!(f, "Body exceeds the limit of {} bytes", limit)
+            }
+            DownloadError::BodyReadTimeout { url, timeout } => {
+                write!(
+                    f,
+                    "Reading the body of {} took longer than {:?}",
+                    url, timeout
+                )
+            }
+            DownloadError::Decode { reason, snippet } => {
+                write!(
+                    f,
+                    "Failed to decode body: {} (body starts with {:?})",
+                    reason, snippet
+                )
+            }
+            DownloadError::InvalidUtf8 { offset } => {
+                write!(f, "Invalid UTF-8 in body at byte offset {}", offset)
+            }
+            DownloadError::InvalidResponse { reason } => {
+                write!(f, "Response rejected: {}", reason)
+            }
+            DownloadError::UnexpectedContentType {
+                expected,
+                got,
+                snippet,
+            } => {
+                write!(
+                    f,
+                    "Expected {} content, got {:?} (body starts with {:?})",
+                    expected, got, snippet
+                )
+            }
+            DownloadError::Cancelled => write!(f, "Download cancelled"),
+            DownloadError::DeadlineExceeded => write!(f, "Global download deadline exceeded"),
+            DownloadError::TlsVersionTooLow { url, min_version } => {
+                write!(
+                    f,
+                    "Server for {} only supports TLS versions older than the minimum of {}",
+                    url, min_version
+                )
+            }
+            DownloadError::ResourceExhausted { limit } => {
+                write!(f, "Refused: {} connections already open", limit)
+            }
+            DownloadError::UnsupportedUrl { url, reason } => {
+                write!(f, "Cannot download {}: {}", url, reason)
+            }
+            DownloadError::ProbeFailed { url, stage, reason } => {
+                write!(f, "Probe of {} failed at {}: {}", url, stage, reason)
+            }
+            DownloadError::TokenRequestFailed { url, reason } => {
+                write!(f, "Failed to obtain a token from {}: {}", url, reason)
+            }
+            DownloadError::UnexpectedUpgrade { protocol } => {
+                write!(
+                    f,
+                    "Server switched protocols to {:?}, which is not supported",
+                    protocol
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<DownloadError> for SpiderError {
+    fn from(err: DownloadError) -> Self {
+        SpiderError::GeneralError(err.to_string())
+    }
+}
+
+/// A failed download that keeps downloader errors structured.
+///
+/// ```rust,ignore
+/// match downloader.try_download(request).await {
+///     Err(DownloadFailure::Download(DownloadError::RetriesExhausted { attempts })) => {
+///         for attempt in &attempts {
+///             eprintln!("{}", attempt);
+///         }
+///     }
+///     other => { /* ... */ }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum DownloadFailure {
+    /// A condition raised by the downloader itself.
+    Download(DownloadError),
+    /// Any other error, e.g. a transport error that wasn't retried.
+    Other(SpiderError),
+}
+
+impl DownloadFailure {
+    /// Returns the downloader error, if this is one.
+    pub fn download_error(&self) -> Option<&DownloadError> {
+        match self {
+            DownloadFailure::Download(err) => Some(err),
+            DownloadFailure::Other(_) => None,
+        }
+    }
+
+    /// Returns the failed attempts when retries ran out.
+    pub fn attempts(&self) -> Option<&[AttemptError]> {
+        match self {
+            DownloadFailure::Download(DownloadError::RetriesExhausted { attempts }) => {
+                Some(attempts)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DownloadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadFailure::Download(err) => err.fmt(f),
+            DownloadFailure::Other(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DownloadFailure {}
+
+impl From<DownloadError> for DownloadFailure {
+    fn from(err: DownloadError) -> Self {
+        DownloadFailure::Download(err)
+    }
+}
+
+impl From<SpiderError> for DownloadFailure {
+    fn from(err: SpiderError) -> Self {
+        DownloadFailure::Other(err)
+    }
+}
+
+impl From<DownloadFailure> for SpiderError {
+    fn from(err: DownloadFailure) -> Self {
+        match err {
+            DownloadFailure::Download(err) => err.into(),
+            DownloadFailure::Other(err) => err,
+        }
+    }
+}
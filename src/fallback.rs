@@ -0,0 +1,97 @@
+//! Chaining two downloaders, falling back to the second on failure.
+//!
+//! A typical setup tries a fast direct downloader first and retries with a
+//! heavier one when the first is blocked:
+//!
+//! ```rust,ignore
+//! let downloader = FallbackDownloader::new(direct, impersonating, |result| match result {
+//!     Ok(response) => matches!(response.status.as_u16(), 403 | 429),
+//!     Err(_) => true,
+//! });
+//! ```
+
+use crate::Downloader;
+use async_trait::async_trait;
+use log::debug;
+use spider_util::error::SpiderError;
+use spider_util::request::Request;
+use spider_util::response::Response;
+use url::Url;
+
+/// A `Downloader` that retries a request with `secondary` when `primary`'s result is rejected.
+///
+/// The predicate receives the primary result and returns `true` to try the
+/// secondary downloader, whose result is then returned as-is.
+pub struct FallbackDownloader<P, S, F>
+where
+    P: Downloader,
+    S: Downloader,
+    F: Fn(&Result<Response, SpiderError>) -> bool + Send + Sync + 'static,
+{
+    primary: P,
+    secondary: S,
+    predicate: F,
+}
+
+impl<P, S, F> FallbackDownloader<P, S, F>
+where
+    P: Downloader,
+    S: Downloader,
+    F: Fn(&Result<Response, SpiderError>) -> bool + Send + Sync + 'static,
+{
+    /// Creates a downloader trying `primary`, then `secondary` when `predicate` says so.
+    pub fn new(primary: P, secondary: S, predicate: F) -> Self {
+        FallbackDownloader {
+            primary,
+            secondary,
+            predicate,
+        }
+    }
+
+    /// Returns the downloader tried first.
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns the downloader used as a fallback.
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+}
+
+#[async_trait]
+impl<P, S, F> Downloader for FallbackDownloader<P, S, F>
+where
+    P: Downloader,
+    S: Downloader,
+    F: Fn(&Result<Response, SpiderError>) -> bool + Send + Sync + 'static,
+{
+    type Client = P::Client;
+
+    async fn download(&self, request: Request) -> Result<Response, SpiderError> {
+        let retry = request.clone();
+        let result = self.primary.download(request).await;
+        if !(self.predicate)(&result) {
+            return result;
+        }
+        debug!("Falling back to the secondary downloader for {}", retry.url);
+        self.secondary.download(retry).await
+    }
+
+    /// Returns the primary downloader's client.
+    fn client(&self) -> &Self::Client {
+        self.primary.client()
+    }
+
+    /// Fingerprints like the primary downloader.
+    fn fingerprint(&self, request: &Request) -> String {
+        self.primary.fingerprint(request)
+    }
+
+    /// Accepts `url` if either downloader does.
+    fn would_allow(&self, url: &Url) -> Result<(), SpiderError> {
+        self.primary
+            .would_allow(url)
+            .or_else(|_| self.secondary.would_allow(url))
+    }
+}
@@ -0,0 +1,36 @@
+//! Content hashing for change detection.
+
+use sha2::{Digest, Sha256};
+
+/// Hash algorithm used by `Downloader::download_with_hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    /// SHA-256, 32 bytes.
+    #[default]
+    Sha256,
+    /// XXH3 64-bit, 8 bytes big-endian. Much faster, but not collision-resistant.
+    #[cfg(feature = "xxhash")]
+    Xxh3,
+}
+
+impl HashAlgo {
+    /// Returns the digest of `data`.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgo::Sha256 => Sha256::digest(data).to_vec(),
+            #[cfg(feature = "xxhash")]
+            HashAlgo::Xxh3 => xxhash_rust::xxh3::xxh3_64(data).to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Lowercase hex encoding of `bytes`.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(DIGITS[(byte >> 4) as usize] as char);
+        out.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
@@ -0,0 +1,133 @@
+//! Per-host clients and their idle eviction.
+//!
+//! The downloader builds one reqwest client per host, each with its own
+//! connection pool. A crawl touching many hosts would keep all of them alive,
+//! so clients left unused for longer than a TTL can be evicted, either by a
+//! sweep run lazily while clients are looked up or by a background task.
+//!
+//! Clients routing through a proxy are kept here too, keyed by host and proxy.
+
+use log::debug;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, watch};
+
+/// How idle per-host clients are swept, see `ReqwestClientDownloader::host_client_idle_ttl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdleEvictionMode {
+    /// Sweep while looking up a client, at most once per interval.
+    #[default]
+    Lazy,
+    /// Sweep from a background task every interval, until `shutdown`.
+    Background,
+}
+
+/// A host's client and when it was last handed out.
+struct HostClient {
+    client: Client,
+    last_used: Mutex<Instant>,
+}
+
+impl HostClient {
+    fn touch(&self, now: Instant) -> Client {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = now;
+        self.client.clone()
+    }
+
+    fn idle_for(&self, now: Instant) -> Duration {
+        let last_used = *self.last_used.lock().unwrap_or_else(|e| e.into_inner());
+        now.saturating_duration_since(last_used)
+    }
+}
+
+/// The per-host clients of a downloader, shared by its clones.
+pub(crate) struct HostClients {
+    clients: RwLock<HashMap<String, HostClient>>,
+    /// When the last lazy sweep ran
+    last_sweep: Mutex<Option<Instant>>,
+    sweeper_started: AtomicBool,
+    shutdown: watch::Sender<bool>,
+}
+
+impl HostClients {
+    pub(crate) fn new() -> Self {
+        HostClients {
+            clients: RwLock::new(HashMap::new()),
+            last_sweep: Mutex::new(None),
+            sweeper_started: AtomicBool::new(false),
+            shutdown: watch::Sender::new(false),
+        }
+    }
+
+    /// Returns the client for `host`, marking it as used at `now`.
+    pub(crate) async fn get(&self, host: &str, now: Instant) -> Option<Client> {
+        let clients = self.clients.read().await;
+        clients.get(host).map(|entry| entry.touch(now))
+    }
+
+    /// Stores `client` for `host` unless another task stored one first.
+    ///
+    /// Returns the client to use and the number of clients now held.
+    pub(crate) async fn insert(&self, host: &str, client: Client, now: Instant) -> (Client, usize) {
+        let mut clients = self.clients.write().await;
+        if let Some(existing) = clients.get(host) {
+            return (existing.touch(now), clients.len());
+        }
+        clients.insert(
+            host.to_string(),
+            HostClient {
+                client: client.clone(),
+                last_used: Mutex::new(now),
+            },
+        );
+        (client, clients.len())
+    }
+
+    /// Drops the clients unused for `ttl` or longer, returning how many are left.
+    pub(crate) async fn evict_idle(&self, ttl: Duration, now: Instant) -> usize {
+        let mut clients = self.clients.write().await;
+        let before = clients.len();
+        clients.retain(|_, entry| entry.idle_for(now) < ttl);
+        if clients.len() < before {
+            debug!(
+                "Evicted {} idle host clients ({} left)",
+                before - clients.len(),
+                clients.len()
+            );
+        }
+        clients.len()
+    }
+
+    /// Returns whether a lazy sweep is due, recording it as run if so.
+    pub(crate) fn lazy_sweep_due(&self, now: Instant, interval: Duration) -> bool {
+        let mut last_sweep = self.last_sweep.lock().unwrap_or_else(|e| e.into_inner());
+        match *last_sweep {
+            Some(last) if now.saturating_duration_since(last) < interval => false,
+            _ => {
+                *last_sweep = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Returns `true` exactly once, for the caller that should start the background sweeper.
+    pub(crate) fn claim_sweeper(&self) -> bool {
+        !self.sweeper_started.swap(true, Ordering::AcqRel)
+    }
+
+    /// Returns a receiver notified when `shutdown` is called or the clients are dropped.
+    pub(crate) fn subscribe_shutdown(&self) -> watch::Receiver<bool> {
+        self.shutdown.subscribe()
+    }
+
+    pub(crate) fn is_shut_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+}
@@ -2,6 +2,19 @@
 //!
 //! Traits and implementations for HTTP downloaders in the `spider-lib` framework.
 //!
+//! ## Features
+//!
+//! - `reqwest` (default): the `ReqwestClientDownloader` implementation.
+//! - `rustls-tls` (default) / `native-tls`: TLS backend used by the reqwest client.
+//! - `xxhash`: the `HashAlgo::Xxh3` content hash.
+//! - `brotli` / `zstd`: decoding of `br` and `zstd` bodies when decompression is enabled.
+//!
+//! With `default-features = false` the reqwest client is left out together
+//! with what only it uses: proxies, download stats and soft-404 detection, and
+//! the `regex`, `md-5` and tokio `net` dependencies. The traits, the wrapping
+//! downloaders (caching, fallback, replay) and the request and response
+//! helpers are still built.
+//!
 //! ## Example
 //!
 //! ```rust,ignore
@@ -25,8 +38,105 @@
 //! }
 //! ```
 
+mod accept;
+mod body_ext;
+mod cache;
+mod clock;
+mod cookie;
+mod cost;
+mod decompress;
+#[cfg(feature = "reqwest")]
+mod digest_auth;
+#[cfg(feature = "reqwest")]
+mod dns;
+mod error;
+mod fallback;
+mod hash;
+#[cfg(feature = "reqwest")]
+mod host_clients;
+mod meta;
+#[cfg(feature = "reqwest")]
+mod meta_refresh;
+#[cfg(feature = "reqwest")]
+mod metrics;
+mod multipart;
+mod oauth2;
+#[cfg(feature = "rustls-tls")]
+mod probe;
+#[cfg(feature = "reqwest")]
+mod proxy;
+mod rate_limit;
+mod replay;
+mod request_ext;
+#[cfg(feature = "reqwest")]
 mod reqwest_client;
+mod response_ext;
+mod retry;
+mod signing;
+#[cfg(feature = "reqwest")]
+mod soft404;
+mod stream;
+mod template;
+mod text;
+#[cfg(feature = "reqwest")]
+mod tls;
 mod traits;
+mod upload;
+mod url_normalize;
+mod validate;
 
+pub use accept::AcceptPreset;
+pub use body_ext::BodyExt;
+pub use cache::{CachingDownloader, FingerprintFn};
+pub use clock::{Clock, SystemClock, TokioClock};
+pub use cookie::Cookie;
+pub use cost::RequestCost;
+#[cfg(feature = "reqwest")]
+pub use dns::AddressFamily;
+pub use error::{AttemptError, AttemptErrorKind, DownloadError, DownloadFailure};
+pub use fallback::FallbackDownloader;
+pub use hash::HashAlgo;
+#[cfg(feature = "reqwest")]
+pub use host_clients::IdleEvictionMode;
+pub use meta::{
+    META_CACHE, META_CONTENT_HASH, META_COOKIES, META_DECODED_BYTES, META_DISABLE_POOL,
+    META_DOWNGRADED, META_DOWNLOADER_CONFIG, META_DRY_RUN, META_ENCODED_BYTES, META_FETCHED_AT,
+    META_FINGERPRINT, META_IDEMPOTENCY_KEY, META_MALFORMED_REDIRECT,
+    META_ORIGINAL_CONTENT_ENCODING, META_ORIGINAL_URL, META_PRIORITY, META_RATE_LIMIT,
+    META_REDIRECT_CHAIN, META_REMOTE_ADDR, META_REMOVE_HEADERS, META_REQUEST_ID, META_SAMPLED_OUT,
+    META_SENT_REQUEST, META_SOFT_404, META_TAG, META_TRUNCATED,
+};
+#[cfg(feature = "reqwest")]
+pub use metrics::{
+    BodySizeSummary, DEFAULT_STATS_TAG, DownloadStats, METRIC_BODY_BYTES, METRIC_HOST_CLIENTS,
+    MetricsSink, RequestCounts, TagStats,
+};
+pub use multipart::MultipartPart;
+pub use oauth2::OAuth2Provider;
+#[cfg(feature = "rustls-tls")]
+pub use probe::ProbeResult;
+#[cfg(feature = "reqwest")]
+pub use proxy::{ProxyConfig, ProxyHealth, ProxyPool, ProxyRotation, ProxyState, UrlMatcher};
+pub use rate_limit::RateLimitInfo;
+pub use replay::{FixtureStore, Offline, RecordingDownloader, ReplayDownloader};
+pub use request_ext::{META_QUERY, RequestExt};
+#[cfg(feature = "reqwest")]
 pub use reqwest_client::ReqwestClientDownloader;
-pub use traits::{Downloader, SimpleHttpClient};
+pub use response_ext::ResponseExt;
+pub use retry::{
+    Jitter, META_MAX_RETRIES, META_RETRY_BACKOFF_MS, RetryAction, RetryPolicy, parse_retry_after,
+};
+pub use signing::{HmacSha256Signer, RequestSigner, constant_time_eq};
+#[cfg(feature = "reqwest")]
+pub use soft404::{SoftNotFoundDetector, SoftNotFoundHeuristic};
+pub use spider_util::request::{Body, Request};
+pub use spider_util::response::Response;
+pub use stream::{BodyStream, StreamingResponse};
+pub use template::RequestTemplate;
+pub use text::{TextDecodePolicy, decode_text};
+#[cfg(feature = "reqwest")]
+pub use tls::{TlsConfig, TlsVersion};
+pub use traits::{BatchMode, Downloader, HEALTH_CHECK_TIMEOUT, SimpleHttpClient};
+pub use upload::{UploadBody, UploadProgress};
+pub use url_normalize::normalize_url;
+pub use validate::{AllowedStatuses, MinBodySize, RequiredHeaders, ResponseValidator};
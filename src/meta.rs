@@ -0,0 +1,54 @@
+//! Reserved `meta` keys written by the downloader.
+
+/// The request fingerprint, stored on every `Response` produced by a download.
+pub const META_FINGERPRINT: &str = "fingerprint";
+/// The request URL as given, before the fragment was stripped.
+pub const META_ORIGINAL_URL: &str = "original_url";
+/// The `Idempotency-Key` header value sent with the request.
+pub const META_IDEMPOTENCY_KEY: &str = "idempotency_key";
+/// Size of the body as received on the wire, set when the downloader decompressed it.
+pub const META_ENCODED_BYTES: &str = "encoded_bytes";
+/// Size of the body after decompression, set when the downloader decompressed it.
+pub const META_DECODED_BYTES: &str = "decoded_bytes";
+/// The socket address the response was received from, when the backend reports it.
+pub const META_REMOTE_ADDR: &str = "remote_addr";
+/// Correlation ID propagated as a request header; read from the request if set.
+pub const META_REQUEST_ID: &str = "request_id";
+/// URLs reached through meta-refresh redirects, in the order they were followed.
+pub const META_REDIRECT_CHAIN: &str = "redirect_chain";
+/// Set to `true` on a request to send it over a fresh, unpooled connection.
+pub const META_DISABLE_POOL: &str = "disable_pool";
+/// Set to `true` on responses synthesized by a dry-run downloader.
+pub const META_DRY_RUN: &str = "dry_run";
+/// Set to `true` on responses synthesized for requests skipped by sampling.
+pub const META_SAMPLED_OUT: &str = "sampled_out";
+/// Set to `true` on redirect responses that lack a `Location` header; they are returned unfollowed.
+pub const META_MALFORMED_REDIRECT: &str = "malformed_redirect";
+/// Hex digest of the response body, set by `Downloader::download_with_hash`.
+pub const META_CONTENT_HASH: &str = "content_hash";
+/// Set to `true` when the body was cut short by `download_head_bytes`.
+pub const META_TRUNCATED: &str = "truncated";
+/// Set to `true` when a `SoftNotFoundDetector` flagged the response as a missing page.
+pub const META_SOFT_404: &str = "soft_404";
+/// Object of cookie names to values, sent in the `Cookie` header of this request only.
+pub const META_COOKIES: &str = "cookies";
+/// Request urgency from 0 (highest) to 7 (lowest), sent as an RFC 9218 `Priority` header.
+pub const META_PRIORITY: &str = "priority";
+/// Array of header names to drop from a request after the downloader's defaults are applied.
+pub const META_REMOVE_HEADERS: &str = "remove_headers";
+/// Unix time in milliseconds at which the response was received.
+pub const META_FETCHED_AT: &str = "fetched_at";
+/// Object describing how the response was fetched: `user_agent`, `proxy` and `protocol`.
+pub const META_DOWNLOADER_CONFIG: &str = "downloader_config";
+/// Per-request cache mode for `CachingDownloader`: `"no-cache"` bypasses the cache, `"refresh"` revalidates.
+pub const META_CACHE: &str = "cache";
+/// Object with the `method`, `url` and redacted `headers` of the request as sent, when recording is enabled.
+pub const META_SENT_REQUEST: &str = "sent_request";
+/// Set to `true` on responses fetched over plain HTTP after the HTTPS attempt failed its TLS handshake.
+pub const META_DOWNGRADED: &str = "downgraded";
+/// Object of normalized `RateLimitInfo` values, set when the response carried rate-limit headers.
+pub const META_RATE_LIMIT: &str = "rate_limit";
+/// `Content-Encoding` of the body as received, e.g. `gzip`, set when the downloader decompressed it.
+pub const META_ORIGINAL_CONTENT_ENCODING: &str = "original_content_encoding";
+/// Name of the logical job a request belongs to, e.g. `"reviews"`, for per-tag stats.
+pub const META_TAG: &str = "tag";
@@ -0,0 +1,76 @@
+//! Detection of `<meta http-equiv="refresh">` redirects in HTML bodies.
+//!
+//! This is a lightweight scan of the start of the document, not an HTML
+//! parser: it looks at `<meta>` tags only and understands the usual
+//! `content="<delay>; url=<target>"` form.
+
+use url::Url;
+
+/// Number of leading body bytes scanned for a meta-refresh tag.
+pub(crate) const SCAN_BYTES: usize = 16 * 1024;
+
+/// Returns the meta-refresh target in `body`, resolved against `base`.
+pub(crate) fn find_meta_refresh(body: &[u8], base: &Url) -> Option<Url> {
+    let prefix = &body[..body.len().min(SCAN_BYTES)];
+    let html = String::from_utf8_lossy(prefix);
+    let lower = html.to_ascii_lowercase();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta") {
+        let start = pos + start;
+        let end = lower[start..].find('>').map_or(lower.len(), |e| start + e);
+        let tag = &html[start..end];
+        pos = end;
+
+        let is_refresh = attribute(tag, "http-equiv")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"));
+        if !is_refresh {
+            continue;
+        }
+        let content = attribute(tag, "content")?;
+        let target = refresh_target(&content)?;
+        return base.join(&target).ok();
+    }
+    None
+}
+
+/// Extracts the value of attribute `name` from a single tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let at = search + found;
+        search = at + name.len();
+        // Must be a whole attribute name, followed by `=`.
+        let preceded_ok = lower[..at]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace());
+        let rest = tag[search..].trim_start();
+        if !preceded_ok || !rest.starts_with('=') {
+            continue;
+        }
+        let value = rest[1..].trim_start();
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or("").to_string(),
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == '/')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        });
+    }
+    None
+}
+
+/// Parses the target out of a refresh `content` value such as `0; url=/next`.
+fn refresh_target(content: &str) -> Option<String> {
+    let (_, rest) = content.split_once([';', ','])?;
+    let rest = rest.trim();
+    let target = match rest.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url=") => &rest[4..],
+        _ => rest,
+    };
+    let target = target.trim().trim_matches(|c| c == '\'' || c == '"');
+    (!target.is_empty()).then(|| target.to_string())
+}
@@ -0,0 +1,224 @@
+//! Metrics hooks and built-in summaries for the downloader.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Histogram of response body sizes in bytes.
+pub const METRIC_BODY_BYTES: &str = "downloader.response_body_bytes";
+/// Gauge of the number of per-host clients held by the downloader.
+pub const METRIC_HOST_CLIENTS: &str = "downloader.host_clients";
+/// Tag under which requests without a `tag` in their meta are counted.
+pub const DEFAULT_STATS_TAG: &str = "default";
+
+/// Receives metrics emitted by a downloader.
+///
+/// Implement this to forward downloader metrics to Prometheus, StatsD or any
+/// other backend.
+pub trait MetricsSink: Send + Sync {
+    /// Records one sample of a histogram metric.
+    fn histogram(&self, name: &str, value: f64);
+
+    /// Records the current value of a gauge metric. Ignored by default.
+    fn gauge(&self, _name: &str, _value: f64) {}
+}
+
+/// A summary of recent response body sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BodySizeSummary {
+    /// Number of responses seen since creation.
+    pub count: u64,
+    /// Total body bytes seen since creation.
+    pub total_bytes: u64,
+    /// Median body size over the recent window.
+    pub p50: usize,
+    /// 95th percentile body size over the recent window.
+    pub p95: usize,
+}
+
+/// Request outcomes counted by a downloader.
+///
+/// A success is a response with a status below 400; a failure is an error or a
+/// 4xx/5xx response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestCounts {
+    /// Requests sent.
+    pub requests: u64,
+    /// Requests that ended in a success.
+    pub successes: u64,
+    /// Requests that ended in a failure.
+    pub failures: u64,
+}
+
+impl RequestCounts {
+    fn record(&mut self, success: bool) {
+        self.requests += 1;
+        if success {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+    }
+}
+
+/// Request counters since the downloader was created or its stats were reset.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DownloadStats {
+    /// Counts across all hosts.
+    pub total: RequestCounts,
+    /// Counts per lowercase host name.
+    pub per_host: HashMap<String, RequestCounts>,
+}
+
+/// Counters for the requests sharing a tag.
+///
+/// A success is a response with a status below 400; a failure is an error or a
+/// 4xx/5xx response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TagStats {
+    /// Requests sent.
+    pub requests: u64,
+    /// Requests that ended in a success.
+    pub successes: u64,
+    /// Requests that ended in a failure.
+    pub failures: u64,
+    /// Failures that produced no response at all, e.g. timeouts.
+    pub errors: u64,
+    /// Body bytes received, after decompression.
+    pub bytes: u64,
+}
+
+/// Thread-safe request counters behind `DownloadStats`.
+#[derive(Debug, Default)]
+pub(crate) struct StatsTracker {
+    requests: AtomicU64,
+    successes: AtomicU64,
+    failures: AtomicU64,
+    per_host: Mutex<HashMap<String, RequestCounts>>,
+    per_tag: Mutex<HashMap<String, TagStats>>,
+}
+
+impl StatsTracker {
+    pub(crate) fn record(&self, host: &str, success: bool) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if success {
+            self.successes.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut per_host = self.per_host.lock().unwrap_or_else(|e| e.into_inner());
+        per_host
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .record(success);
+    }
+
+    /// Counts a request under `tag`; `bytes` is `None` when it produced no response.
+    pub(crate) fn record_tag(&self, tag: &str, success: bool, bytes: Option<usize>) {
+        let mut per_tag = self.per_tag.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = per_tag.entry(tag.to_string()).or_default();
+        stats.requests += 1;
+        if success {
+            stats.successes += 1;
+        } else {
+            stats.failures += 1;
+        }
+        match bytes {
+            Some(bytes) => stats.bytes += bytes as u64,
+            None => stats.errors += 1,
+        }
+    }
+
+    pub(crate) fn by_tag(&self) -> HashMap<String, TagStats> {
+        self.per_tag
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn snapshot(&self) -> DownloadStats {
+        let per_host = self.per_host.lock().unwrap_or_else(|e| e.into_inner());
+        DownloadStats {
+            total: RequestCounts {
+                requests: self.requests.load(Ordering::Relaxed),
+                successes: self.successes.load(Ordering::Relaxed),
+                failures: self.failures.load(Ordering::Relaxed),
+            },
+            per_host: per_host.clone(),
+        }
+    }
+
+    pub(crate) fn host(&self, host: &str) -> RequestCounts {
+        let per_host = self.per_host.lock().unwrap_or_else(|e| e.into_inner());
+        per_host
+            .get(&host.to_ascii_lowercase())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn reset(&self) {
+        let mut per_host = self.per_host.lock().unwrap_or_else(|e| e.into_inner());
+        per_host.clear();
+        self.per_tag
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.requests.store(0, Ordering::Relaxed);
+        self.successes.store(0, Ordering::Relaxed);
+        self.failures.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Tracks body sizes: exact totals plus a bounded window for percentiles.
+#[derive(Debug)]
+pub(crate) struct BodySizeTracker {
+    count: AtomicU64,
+    total_bytes: AtomicU64,
+    window: Mutex<VecDeque<usize>>,
+}
+
+impl BodySizeTracker {
+    /// Number of recent samples kept for percentile estimates.
+    const WINDOW: usize = 1024;
+
+    pub(crate) fn new() -> Self {
+        BodySizeTracker {
+            count: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
+            window: Mutex::new(VecDeque::with_capacity(Self::WINDOW)),
+        }
+    }
+
+    pub(crate) fn record(&self, bytes: usize) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.len() == Self::WINDOW {
+            window.pop_front();
+        }
+        window.push_back(bytes);
+    }
+
+    pub(crate) fn summary(&self) -> BodySizeSummary {
+        let mut sizes: Vec<usize> = {
+            let window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+            window.iter().copied().collect()
+        };
+        sizes.sort_unstable();
+        BodySizeSummary {
+            count: self.count.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            p50: percentile(&sizes, 50),
+            p95: percentile(&sizes, 95),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice; 0 when empty.
+fn percentile(sorted: &[usize], pct: usize) -> usize {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
@@ -0,0 +1,268 @@
+//! Parsing of streamed multipart bodies.
+//!
+//! Push-style endpoints such as MJPEG cameras answer with
+//! `multipart/x-mixed-replace` and keep sending parts for as long as the
+//! connection is open. `StreamingResponse::multipart` splits such a body into
+//! `MultipartPart`s as they arrive, using the boundary from `Content-Type`.
+//!
+//! Parsing follows RFC 2046 section 5.1 but tolerates what servers commonly
+//! get wrong: bare `LF` line endings and a missing close delimiter. A part with
+//! a `Content-Length` header is read by length, so a delimiter-like sequence in
+//! its binary body can't cut it short; other parts end at the next delimiter.
+
+use crate::error::DownloadError;
+use crate::stream::BodyStream;
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::stream::{self, Stream, StreamExt};
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use spider_util::error::SpiderError;
+
+/// One part of a multipart body.
+#[derive(Debug, Clone)]
+pub struct MultipartPart {
+    /// The part's own headers, e.g. its `Content-Type`.
+    pub headers: HeaderMap,
+    /// The part's body.
+    pub body: Bytes,
+}
+
+/// Returns the boundary of a `multipart/*` content type, or `None` for other types.
+pub(crate) fn boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let mut params = content_type.split(';');
+    let essence = params.next()?.trim().to_ascii_lowercase();
+    if !essence.starts_with("multipart/") {
+        return None;
+    }
+    params.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("boundary") {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Splits `body` into parts delimited by `boundary`.
+pub(crate) fn parts(
+    body: BodyStream,
+    boundary: &str,
+) -> impl Stream<Item = Result<MultipartPart, SpiderError>> + Send {
+    let parser = Parser {
+        buf: BytesMut::new(),
+        delimiter: format!("--{}", boundary).into_bytes(),
+        phase: Phase::Preamble,
+        scan_from: 0,
+    };
+    stream::unfold(
+        (body, parser, false),
+        |(mut body, mut parser, mut done)| async move {
+            loop {
+                match parser.step() {
+                    Step::Part(part) => return Some((Ok(part), (body, parser, done))),
+                    Step::End => return None,
+                    Step::NeedMore if done => {
+                        let error = parser.finish()?;
+                        return Some((Err(error), (body, parser, done)));
+                    }
+                    Step::NeedMore => {}
+                }
+                match body.next().await {
+                    Some(Ok(chunk)) => parser.buf.extend_from_slice(&chunk),
+                    Some(Err(e)) => {
+                        parser.phase = Phase::Finished;
+                        return Some((Err(e), (body, parser, true)));
+                    }
+                    None => done = true,
+                }
+            }
+        },
+    )
+}
+
+enum Phase {
+    /// Looking for the next delimiter, discarding what comes before it.
+    Preamble,
+    /// Right after a delimiter: either the close marker `--` or the rest of the line.
+    AfterDelimiter,
+    /// Reading the part headers up to the blank line.
+    Headers,
+    /// Reading the part body, by `Content-Length` when the part has one.
+    Body {
+        headers: HeaderMap,
+        len: Option<usize>,
+    },
+    /// The close delimiter or an error was seen.
+    Finished,
+}
+
+enum Step {
+    Part(MultipartPart),
+    NeedMore,
+    End,
+}
+
+struct Parser {
+    buf: BytesMut,
+    /// `--` followed by the boundary.
+    delimiter: Vec<u8>,
+    phase: Phase,
+    /// Where the next delimiter search starts, so chunks aren't rescanned.
+    scan_from: usize,
+}
+
+impl Parser {
+    /// Parses as much of the buffer as possible.
+    fn step(&mut self) -> Step {
+        loop {
+            match &mut self.phase {
+                Phase::Preamble => match find(&self.buf, &self.delimiter, self.scan_from) {
+                    Some(pos) => {
+                        self.buf.advance(pos + self.delimiter.len());
+                        self.scan_from = 0;
+                        self.phase = Phase::AfterDelimiter;
+                    }
+                    None => {
+                        // Keep only a tail that could be the start of a delimiter.
+                        let keep = self.delimiter.len() - 1;
+                        if self.buf.len() > keep {
+                            self.buf.advance(self.buf.len() - keep);
+                        }
+                        self.scan_from = 0;
+                        return Step::NeedMore;
+                    }
+                },
+                Phase::AfterDelimiter => {
+                    if self.buf.len() < 2 {
+                        return Step::NeedMore;
+                    }
+                    if self.buf.starts_with(b"--") {
+                        self.phase = Phase::Finished;
+                        return Step::End;
+                    }
+                    let Some(end) = self.buf.iter().position(|b| *b == b'\n') else {
+                        return Step::NeedMore;
+                    };
+                    self.buf.advance(end + 1);
+                    self.phase = Phase::Headers;
+                }
+                Phase::Headers => {
+                    let Some(end) = header_block_end(&self.buf) else {
+                        return Step::NeedMore;
+                    };
+                    let block = self.buf.split_to(end);
+                    let headers = parse_headers(&block);
+                    let len = headers
+                        .get(CONTENT_LENGTH)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse().ok());
+                    self.phase = Phase::Body { headers, len };
+                }
+                Phase::Body { headers, len } => {
+                    let body = match len {
+                        Some(len) => {
+                            if self.buf.len() < *len {
+                                return Step::NeedMore;
+                            }
+                            self.buf.split_to(*len).freeze()
+                        }
+                        None => {
+                            // Delimiters start on a new line; the line break belongs to them.
+                            let Some(pos) =
+                                find_line_delimiter(&self.buf, &self.delimiter, self.scan_from)
+                            else {
+                                self.scan_from =
+                                    self.buf.len().saturating_sub(self.delimiter.len() + 1);
+                                return Step::NeedMore;
+                            };
+                            let mut body = self.buf.split_to(pos);
+                            if body.ends_with(b"\r") {
+                                body.truncate(body.len() - 1);
+                            }
+                            self.scan_from = 0;
+                            body.freeze()
+                        }
+                    };
+                    let headers = std::mem::take(headers);
+                    self.phase = Phase::Preamble;
+                    return Step::Part(MultipartPart { headers, body });
+                }
+                Phase::Finished => return Step::End,
+            }
+        }
+    }
+
+    /// Handles the end of the body, returning an error if it cut a part short.
+    fn finish(&mut self) -> Option<SpiderError> {
+        let phase = std::mem::replace(&mut self.phase, Phase::Finished);
+        match phase {
+            // A missing close delimiter is common and harmless.
+            Phase::Preamble | Phase::AfterDelimiter | Phase::Finished => None,
+            Phase::Headers | Phase::Body { .. } => Some(
+                DownloadError::InvalidResponse {
+                    reason: "multipart body ended inside a part".to_string(),
+                }
+                .into(),
+            ),
+        }
+    }
+}
+
+/// Returns the position of `needle` in `haystack`, searching from `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
+/// Returns the position of the line break before a delimiter starting a line.
+fn find_line_delimiter(haystack: &[u8], delimiter: &[u8], from: usize) -> Option<usize> {
+    let mut from = from;
+    loop {
+        let pos = find(haystack, delimiter, from)?;
+        if pos > 0 && haystack[pos - 1] == b'\n' {
+            return Some(pos - 1);
+        }
+        from = pos + 1;
+    }
+}
+
+/// Returns the length of the header block including its terminating blank line.
+fn header_block_end(buf: &[u8]) -> Option<usize> {
+    let mut start = 0;
+    loop {
+        let end = start + buf[start..].iter().position(|b| *b == b'\n')?;
+        let line = &buf[start..end];
+        if line.strip_suffix(b"\r").unwrap_or(line).is_empty() {
+            return Some(end + 1);
+        }
+        start = end + 1;
+    }
+}
+
+/// Parses `Name: value` lines, skipping malformed ones.
+fn parse_headers(block: &[u8]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for line in block.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let Some(colon) = line.iter().position(|b| *b == b':') else {
+            continue;
+        };
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(line[..colon].trim_ascii()),
+            HeaderValue::from_bytes(line[colon + 1..].trim_ascii()),
+        ) else {
+            continue;
+        };
+        headers.append(name, value);
+    }
+    headers
+}
@@ -0,0 +1,280 @@
+//! OAuth2 client-credentials tokens.
+//!
+//! `OAuth2Provider` obtains an access token from a token endpoint with the
+//! `client_credentials` grant (RFC 6749 section 4.4) and caches it until
+//! shortly before it expires. The downloader asks it for an
+//! `Authorization: Bearer` header on every request.
+//!
+//! Refreshes are serialized: when the token is missing or expired, the first
+//! caller fetches a new one while the others wait for it instead of each
+//! hitting the token endpoint. Within the refresh margin the current token is
+//! still valid, so one caller refreshes early while the rest keep using it.
+//!
+//! The margin is capped at half of a token's lifetime, so an endpoint issuing
+//! tokens that live shorter than the margin doesn't get a request per call.
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::DownloadError;
+use crate::response_ext::snippet;
+use crate::traits::SimpleHttpClient;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use http::header::{ACCEPT, AUTHORIZATION};
+use http::{HeaderMap, HeaderValue};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The token endpoint's answer; only the fields used are read.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// A cached token and when it expires.
+#[derive(Clone)]
+struct CachedToken {
+    authorization: HeaderValue,
+    /// `None` when the endpoint gave no lifetime; the token is then kept until rejected
+    expires_at: Option<Instant>,
+    /// When to start refreshing early, at most halfway through the lifetime
+    refresh_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+
+    fn needs_refresh(&self, now: Instant) -> bool {
+        self.refresh_at.is_some_and(|at| now >= at)
+    }
+}
+
+/// Obtains and caches OAuth2 access tokens with the client-credentials grant.
+///
+/// Client credentials are sent with HTTP Basic authentication, as RFC 6749
+/// recommends. Share one provider between downloaders through an `Arc` so
+/// they share its token.
+pub struct OAuth2Provider {
+    http: Arc<dyn SimpleHttpClient>,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    refresh_margin: Duration,
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+    token: Mutex<Option<CachedToken>>,
+    /// Held while a token is fetched, so concurrent callers share one refresh
+    refresh: tokio::sync::Mutex<()>,
+}
+
+impl fmt::Debug for OAuth2Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Provider")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth2Provider {
+    /// Creates a provider fetching tokens from `token_url` through `http`.
+    ///
+    /// Tokens are refreshed 60 seconds before they expire and token requests
+    /// time out after 30 seconds.
+    pub fn new(
+        http: Arc<dyn SimpleHttpClient>,
+        token_url: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Self {
+        OAuth2Provider {
+            http,
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scopes: Vec::new(),
+            refresh_margin: Duration::from_secs(60),
+            timeout: Duration::from_secs(30),
+            clock: Arc::new(SystemClock),
+            token: Mutex::new(None),
+            refresh: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Requests tokens for `scopes`, sent space-separated in the `scope` parameter.
+    pub fn scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Refreshes tokens `margin` before they expire instead of 60 seconds before.
+    ///
+    /// A token living less than twice `margin` is refreshed halfway through
+    /// its lifetime instead.
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Times token requests out after `timeout` instead of 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Replaces the clock used to track token expiry.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the `Authorization: Bearer` value to send, fetching a token if needed.
+    ///
+    /// If an early refresh within the margin fails, the current token is
+    /// returned and the refresh is tried again on the next call. Otherwise a
+    /// failure is reported as `DownloadError::TokenRequestFailed`.
+    pub async fn authorization(&self) -> Result<HeaderValue, DownloadError> {
+        let cached = self.cached();
+        if let Some(token) = &cached {
+            let now = self.clock.now();
+            if !token.needs_refresh(now) {
+                return Ok(token.authorization.clone());
+            }
+            if !token.is_expired(now) {
+                // Still valid: only one caller refreshes, the others keep using it.
+                let Ok(_guard) = self.refresh.try_lock() else {
+                    return Ok(token.authorization.clone());
+                };
+                return match self.refresh_locked(Some(&token.authorization)).await {
+                    Ok(authorization) => Ok(authorization),
+                    Err(e) => {
+                        warn!("Early token refresh from {} failed: {}", self.token_url, e);
+                        Ok(token.authorization.clone())
+                    }
+                };
+            }
+        }
+        let _guard = self.refresh.lock().await;
+        self.refresh_locked(cached.as_ref().map(|token| &token.authorization))
+            .await
+    }
+
+    /// Drops the cached token if it is `authorization`, e.g. after the server rejected it.
+    ///
+    /// Comparing against the rejected value keeps a token fetched in the
+    /// meantime from being dropped as well.
+    pub fn invalidate(&self, authorization: &HeaderValue) {
+        let mut token = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        if token
+            .as_ref()
+            .is_some_and(|t| t.authorization == *authorization)
+        {
+            debug!("Dropping rejected token from {}", self.token_url);
+            *token = None;
+        }
+    }
+
+    fn cached(&self) -> Option<CachedToken> {
+        self.token.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Fetches a token unless another caller did while this one waited for the lock.
+    ///
+    /// `seen` is the token the caller found cached; a valid token other than
+    /// it was fetched in the meantime and is used even inside the margin.
+    async fn refresh_locked(
+        &self,
+        seen: Option<&HeaderValue>,
+    ) -> Result<HeaderValue, DownloadError> {
+        if let Some(token) = self.cached() {
+            let now = self.clock.now();
+            let replaced = seen != Some(&token.authorization);
+            if !token.needs_refresh(now) || (replaced && !token.is_expired(now)) {
+                return Ok(token.authorization);
+            }
+        }
+        let token = self.fetch().await?;
+        let authorization = token.authorization.clone();
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) = Some(token);
+        Ok(authorization)
+    }
+
+    async fn fetch(&self) -> Result<CachedToken, DownloadError> {
+        let failed = |reason: String| DownloadError::TokenRequestFailed {
+            url: self.token_url.clone(),
+            reason,
+        };
+
+        // RFC 6749 section 2.3.1: both are form-urlencoded before Basic encoding.
+        let credentials = BASE64.encode(format!(
+            "{}:{}",
+            form_urlencode(&self.client_id),
+            form_urlencode(&self.client_secret)
+        ));
+        let mut basic = HeaderValue::from_str(&format!("Basic {}", credentials))
+            .map_err(|e| failed(format!("invalid client credentials: {}", e)))?;
+        basic.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, basic);
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+
+        let scope = self.scopes.join(" ");
+        let mut form = vec![("grant_type", "client_credentials")];
+        if !scope.is_empty() {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let requested_at = self.clock.now();
+        let (status, body) = self
+            .http
+            .post_form(&self.token_url, &form, headers, self.timeout)
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        if !status.is_success() {
+            return Err(failed(format!("status {}: {}", status, snippet(&body))));
+        }
+        let response: TokenResponse = serde_json::from_slice(&body)
+            .map_err(|e| failed(format!("invalid token response: {}", e)))?;
+        if let Some(token_type) = &response.token_type
+            && !token_type.eq_ignore_ascii_case("bearer")
+        {
+            return Err(failed(format!("unsupported token type {:?}", token_type)));
+        }
+
+        let mut authorization = HeaderValue::from_str(&format!("Bearer {}", response.access_token))
+            .map_err(|e| failed(format!("invalid access token: {}", e)))?;
+        authorization.set_sensitive(true);
+        // Measured from the request, so time spent in flight counts against the lifetime.
+        let lifetime = response.expires_in.map(Duration::from_secs);
+        let expires_at = lifetime.map(|lifetime| requested_at + lifetime);
+        let refresh_at = lifetime
+            .map(|lifetime| requested_at + lifetime - self.refresh_margin.min(lifetime / 2));
+        debug!(
+            "Obtained token from {} (expires in {:?})",
+            self.token_url, lifetime
+        );
+        Ok(CachedToken {
+            authorization,
+            expires_at,
+            refresh_at,
+        })
+    }
+}
+
+/// Encodes `value` as `application/x-www-form-urlencoded`.
+fn form_urlencode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
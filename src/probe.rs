@@ -0,0 +1,142 @@
+//! Connect-only reachability probes.
+//!
+//! A probe opens a TCP connection to a URL's host and port and, for `https`,
+//! completes a TLS handshake, then closes the connection without sending an
+//! HTTP request. Its result tells connectivity problems apart from
+//! application-level ones before a crawl starts.
+//!
+//! The handshake is done with rustls against the Mozilla root certificates
+//! and offers `h2` and `http/1.1` through ALPN, independently of the reqwest
+//! client; per-host `TlsConfig` overrides don't apply to it.
+
+use crate::error::DownloadError;
+use crate::tls::TlsVersion;
+use spider_util::error::SpiderError;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, ProtocolVersion, RootCertStore};
+use url::Url;
+
+/// What a successful probe found out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeResult {
+    /// Address the connection was made to.
+    pub addr: SocketAddr,
+    /// Time taken to establish the TCP connection.
+    pub connect_time: Duration,
+    /// Time taken by the TLS handshake, for `https` URLs.
+    pub tls_handshake_time: Option<Duration>,
+    /// Negotiated TLS version, for `https` URLs.
+    pub tls_version: Option<TlsVersion>,
+    /// Protocol negotiated through ALPN, e.g. `h2`, when the server chose one.
+    pub alpn_protocol: Option<String>,
+}
+
+/// Connects to `addrs` in order, then handshakes for `https`, each step bounded by `timeout`.
+pub(crate) async fn probe(
+    url: &Url,
+    addrs: &[SocketAddr],
+    timeout: Duration,
+) -> Result<ProbeResult, SpiderError> {
+    let failed = |stage: &str, reason: String| DownloadError::ProbeFailed {
+        url: url.to_string(),
+        stage: stage.to_string(),
+        reason,
+    };
+    if addrs.is_empty() {
+        return Err(failed("resolve", "no addresses".to_string()).into());
+    }
+
+    let mut last_error = String::new();
+    let mut connected = None;
+    for addr in addrs {
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => {
+                connected = Some((stream, *addr, started.elapsed()));
+                break;
+            }
+            Ok(Err(e)) => last_error = format!("{}: {}", addr, e),
+            Err(_) => last_error = format!("{}: timed out after {:?}", addr, timeout),
+        }
+    }
+    let Some((stream, addr, connect_time)) = connected else {
+        return Err(failed("connect", last_error).into());
+    };
+
+    if url.scheme() != "https" {
+        return Ok(ProbeResult {
+            addr,
+            connect_time,
+            tls_handshake_time: None,
+            tls_version: None,
+            alpn_protocol: None,
+        });
+    }
+
+    let host = url.host_str().unwrap_or("");
+    // IPv6 literals come bracketed from the URL but not in server names.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| failed("tls", format!("invalid server name: {}", e)))?;
+    let config = tls_config().map_err(|reason| failed("tls", reason))?;
+    let started = Instant::now();
+    let tls = match tokio::time::timeout(
+        timeout,
+        TlsConnector::from(config).connect(server_name, stream),
+    )
+    .await
+    {
+        Ok(Ok(tls)) => tls,
+        Ok(Err(e)) => return Err(failed("tls", e.to_string()).into()),
+        Err(_) => return Err(failed("tls", format!("timed out after {:?}", timeout)).into()),
+    };
+    let tls_handshake_time = started.elapsed();
+
+    let (_, connection) = tls.get_ref();
+    let tls_version = connection
+        .protocol_version()
+        .and_then(|version| match version {
+            ProtocolVersion::TLSv1_2 => Some(TlsVersion::Tls1_2),
+            ProtocolVersion::TLSv1_3 => Some(TlsVersion::Tls1_3),
+            _ => None,
+        });
+    let alpn_protocol = connection
+        .alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned());
+    // Dropping the stream closes the connection.
+    drop(tls);
+
+    Ok(ProbeResult {
+        addr,
+        connect_time,
+        tls_handshake_time: Some(tls_handshake_time),
+        tls_version,
+        alpn_protocol,
+    })
+}
+
+/// The client configuration shared by all probes, built on first use.
+fn tls_config() -> Result<Arc<ClientConfig>, String> {
+    static CONFIG: OnceLock<Result<Arc<ClientConfig>, String>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            // An explicit provider, since reqwest may have enabled another one.
+            let provider = tokio_rustls::rustls::crypto::aws_lc_rs::default_provider();
+            let mut config = ClientConfig::builder_with_provider(Arc::new(provider))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| e.to_string())?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            Ok(Arc::new(config))
+        })
+        .clone()
+}
@@ -0,0 +1,97 @@
+//! Rate-limit signals sent by servers.
+//!
+//! Servers announce throttling with `Retry-After` and a family of rate-limit
+//! headers spelled differently from one API to the next: `X-RateLimit-*`,
+//! `X-Rate-Limit-*` and the IETF draft's `RateLimit-*`. `RateLimitInfo`
+//! gathers them into normalized values. A reset given as a Unix timestamp is
+//! turned into the delay until that time, like a reset given in seconds.
+
+use crate::retry::parse_retry_after;
+use http::HeaderMap;
+use http::header::RETRY_AFTER;
+use serde_json::{Map, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Header name prefixes of the rate-limit header families, checked in order.
+const PREFIXES: [&str; 3] = ["x-ratelimit-", "x-rate-limit-", "ratelimit-"];
+
+/// Reset values above this are Unix timestamps rather than delays in seconds.
+const EPOCH_THRESHOLD: u64 = 1_000_000_000;
+
+/// Normalized rate-limit information from response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimitInfo {
+    /// Delay requested by `Retry-After`.
+    pub retry_after: Option<Duration>,
+    /// Requests allowed in the current window.
+    pub limit: Option<u64>,
+    /// Requests left in the current window.
+    pub remaining: Option<u64>,
+    /// Time until the window resets.
+    pub reset_after: Option<Duration>,
+}
+
+impl RateLimitInfo {
+    /// Reads rate-limit signals from `headers`, or returns `None` if there are none.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let info = RateLimitInfo {
+            retry_after: headers.get(RETRY_AFTER).and_then(parse_retry_after),
+            limit: family_value(headers, "limit"),
+            remaining: family_value(headers, "remaining"),
+            reset_after: family_value(headers, "reset").map(reset_delay),
+        };
+        (info != RateLimitInfo::default()).then_some(info)
+    }
+
+    /// Returns the information as a JSON object, with durations in milliseconds.
+    ///
+    /// Only the values that were present are included, under `retry_after_ms`,
+    /// `limit`, `remaining` and `reset_after_ms`.
+    pub fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        if let Some(retry_after) = self.retry_after {
+            object.insert(
+                "retry_after_ms".to_string(),
+                Value::from(retry_after.as_millis() as u64),
+            );
+        }
+        if let Some(limit) = self.limit {
+            object.insert("limit".to_string(), Value::from(limit));
+        }
+        if let Some(remaining) = self.remaining {
+            object.insert("remaining".to_string(), Value::from(remaining));
+        }
+        if let Some(reset_after) = self.reset_after {
+            object.insert(
+                "reset_after_ms".to_string(),
+                Value::from(reset_after.as_millis() as u64),
+            );
+        }
+        Value::Object(object)
+    }
+}
+
+/// Returns the first parsable `<prefix><field>` header value across the header families.
+fn family_value(headers: &HeaderMap, field: &str) -> Option<u64> {
+    PREFIXES.iter().find_map(|prefix| {
+        let value = headers.get(format!("{prefix}{field}"))?.to_str().ok()?;
+        // Some servers send fractional seconds or a quota policy after `;`.
+        let number = value.split([';', ',']).next()?.trim();
+        number
+            .parse::<u64>()
+            .ok()
+            .or_else(|| number.parse::<f64>().ok().map(|n| n.max(0.0).ceil() as u64))
+    })
+}
+
+/// Turns a reset value, in seconds or as a Unix timestamp, into a delay from now.
+fn reset_delay(value: u64) -> Duration {
+    if value < EPOCH_THRESHOLD {
+        return Duration::from_secs(value);
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Duration::from_secs(value.saturating_sub(now))
+}
@@ -0,0 +1,399 @@
+//! Offline replay of recorded responses.
+//!
+//! This module provides `ReplayDownloader`, a `Downloader` that serves responses
+//! previously recorded to a directory instead of touching the network. Each
+//! recording is stored as two files named after the request fingerprint:
+//! `<fingerprint>.json` holding the URL, status and headers, and
+//! `<fingerprint>.body` holding the raw body bytes.
+//!
+//! Fingerprints come from `Downloader::fingerprint` of the downloader doing the
+//! recording, so they cover the request as it is sent, including query
+//! parameters attached with `RequestExt::with_query`.
+//!
+//! In record-or-replay mode a miss is forwarded to an upstream downloader and
+//! the result is written to the directory, so the next run replays it.
+//! `RecordingDownloader` is the record-only counterpart: it always downloads
+//! and writes what it got, e.g. to build a test corpus from a real crawl. In
+//! both, a recording that can't be written is logged and the response is
+//! still returned.
+
+use crate::Downloader;
+use crate::error::DownloadError;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use spider_util::error::SpiderError;
+use spider_util::request::Request;
+use spider_util::response::Response;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use url::Url;
+use uuid::Uuid;
+
+/// On-disk representation of a recorded response, excluding the body.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    url: String,
+    request_url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// A directory of recorded responses keyed by request fingerprint.
+///
+/// Clones share their write locks, so concurrent saves of one fingerprint
+/// through any of them are serialized.
+#[derive(Debug, Clone)]
+pub struct FixtureStore {
+    dir: PathBuf,
+    /// One lock per fingerprint being saved, dropped once nobody holds it
+    writing: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl FixtureStore {
+    /// Creates a store rooted at `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FixtureStore {
+            dir: dir.into(),
+            writing: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the directory recordings are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn meta_path(&self, fingerprint: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", fingerprint))
+    }
+
+    fn body_path(&self, fingerprint: &str) -> PathBuf {
+        self.dir.join(format!("{}.body", fingerprint))
+    }
+
+    /// Loads the recording for `fingerprint`, attaching the request's meta to it.
+    ///
+    /// Returns `DownloadError::NoRecording` if nothing was recorded for it.
+    pub async fn load(
+        &self,
+        fingerprint: &str,
+        request: Request,
+    ) -> Result<Response, DownloadError> {
+        let meta_path = self.meta_path(fingerprint);
+        let raw = match tokio::fs::read(&meta_path).await {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(DownloadError::NoRecording {
+                    fingerprint: fingerprint.to_string(),
+                });
+            }
+            Err(e) => return Err(invalid(&meta_path, e)),
+        };
+        let recorded: RecordedResponse =
+            serde_json::from_slice(&raw).map_err(|e| invalid(&meta_path, e))?;
+
+        let body_path = self.body_path(fingerprint);
+        let body = tokio::fs::read(&body_path)
+            .await
+            .map_err(|e| invalid(&body_path, e))?;
+
+        let url = Url::parse(&recorded.url).map_err(|e| invalid(&meta_path, e))?;
+        let request_url = Url::parse(&recorded.request_url).map_err(|e| invalid(&meta_path, e))?;
+        let status = StatusCode::from_u16(recorded.status).map_err(|e| invalid(&meta_path, e))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in recorded.headers {
+            let name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&meta_path, e))?;
+            let value = HeaderValue::from_str(&value).map_err(|e| invalid(&meta_path, e))?;
+            headers.append(name, value);
+        }
+
+        Ok(Response {
+            url,
+            status,
+            headers,
+            body: Bytes::from(body),
+            request_url,
+            meta: request.meta,
+            cached: false,
+        })
+    }
+
+    /// Writes `response` to the store under `fingerprint`, overwriting any previous recording.
+    ///
+    /// Each file is written to a temporary name and renamed into place, so a
+    /// concurrent `load` never reads a partially written file. Saves of the
+    /// same fingerprint wait for each other, so one recording's body is never
+    /// paired with another's metadata; other fingerprints are written in
+    /// parallel.
+    pub async fn save(&self, fingerprint: &str, response: &Response) -> Result<(), DownloadError> {
+        let lock = self
+            .writing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(fingerprint.to_string())
+            .or_default()
+            .clone();
+        let result = {
+            let _writing = lock.lock().await;
+            self.write(fingerprint, response).await
+        };
+        let mut writing = self.writing.lock().unwrap_or_else(|e| e.into_inner());
+        // Only the map and this call still hold the lock: nobody else is waiting.
+        if Arc::strong_count(&lock) == 2 {
+            writing.remove(fingerprint);
+        }
+        result
+    }
+
+    async fn write(&self, fingerprint: &str, response: &Response) -> Result<(), DownloadError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| invalid(&self.dir, e))?;
+
+        let recorded = RecordedResponse {
+            url: response.url.to_string(),
+            request_url: response.request_url.to_string(),
+            status: response.status.as_u16(),
+            headers: response
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.as_str().to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+        };
+        let meta_path = self.meta_path(fingerprint);
+        let raw = serde_json::to_vec_pretty(&recorded).map_err(|e| invalid(&meta_path, e))?;
+
+        // Body first, so a reader never sees metadata without its body.
+        write_atomic(&self.body_path(fingerprint), &response.body).await?;
+        write_atomic(&meta_path, &raw).await
+    }
+}
+
+/// Writes `contents` next to `path` under a unique name, then renames it over `path`.
+async fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), DownloadError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".tmp-{}", Uuid::new_v4()));
+    let tmp = PathBuf::from(tmp);
+    if let Err(e) = tokio::fs::write(&tmp, contents).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(invalid(path, e));
+    }
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| invalid(path, e))
+}
+
+fn invalid(path: &Path, reason: impl std::fmt::Display) -> DownloadError {
+    DownloadError::InvalidRecording {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// The upstream of a replay-only `ReplayDownloader`; it never downloads anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Offline;
+
+#[async_trait]
+impl Downloader for Offline {
+    type Client = ();
+
+    async fn download(&self, request: Request) -> Result<Response, SpiderError> {
+        Err(DownloadError::UnsupportedUrl {
+            url: request.url.to_string(),
+            reason: "offline".to_string(),
+        }
+        .into())
+    }
+
+    fn client(&self) -> &Self::Client {
+        &()
+    }
+}
+
+/// A `Downloader` that serves recorded responses instead of using the network.
+///
+/// ```rust,ignore
+/// // Replay only: every miss is an error.
+/// let replay = ReplayDownloader::replay("tests/fixtures");
+///
+/// // Record on miss, replay on hit.
+/// let replay = ReplayDownloader::record_or_replay("tests/fixtures", ReqwestClientDownloader::new());
+/// ```
+pub struct ReplayDownloader<D: Downloader = Offline> {
+    store: FixtureStore,
+    upstream: Option<D>,
+}
+
+impl ReplayDownloader<Offline> {
+    /// Creates a downloader that only replays, returning `DownloadError::NoRecording` on a miss.
+    ///
+    /// Requests are fingerprinted with the default `Downloader::fingerprint`,
+    /// which matches recordings made through a downloader that doesn't
+    /// normalize URLs further.
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        ReplayDownloader {
+            store: FixtureStore::new(dir),
+            upstream: None,
+        }
+    }
+}
+
+impl<D: Downloader> ReplayDownloader<D> {
+    /// Creates a downloader that replays hits and records misses fetched through `upstream`.
+    ///
+    /// Requests are fingerprinted by `upstream`. A miss that can't be written
+    /// to the store is logged and the downloaded response returned anyway.
+    pub fn record_or_replay(dir: impl Into<PathBuf>, upstream: D) -> Self {
+        ReplayDownloader {
+            store: FixtureStore::new(dir),
+            upstream: Some(upstream),
+        }
+    }
+
+    /// Returns the fixture store backing this downloader.
+    pub fn store(&self) -> &FixtureStore {
+        &self.store
+    }
+}
+
+#[async_trait]
+impl<D: Downloader> Downloader for ReplayDownloader<D> {
+    type Client = ();
+
+    async fn download(&self, request: Request) -> Result<Response, SpiderError> {
+        let fingerprint = self.fingerprint(&request);
+
+        let Some(upstream) = &self.upstream else {
+            return Ok(self.store.load(&fingerprint, request).await?);
+        };
+
+        if tokio::fs::try_exists(self.store.meta_path(&fingerprint))
+            .await
+            .unwrap_or(false)
+        {
+            return Ok(self.store.load(&fingerprint, request).await?);
+        }
+
+        let response = upstream.download(request).await?;
+        if let Err(e) = self.store.save(&fingerprint, &response).await {
+            warn!("Failed to record {}: {}", response.url, e);
+        }
+        Ok(response)
+    }
+
+    fn client(&self) -> &Self::Client {
+        &()
+    }
+
+    /// Fingerprints like the upstream downloader, or with the default without one.
+    fn fingerprint(&self, request: &Request) -> String {
+        match &self.upstream {
+            Some(upstream) => upstream.fingerprint(request),
+            None => Offline.fingerprint(request),
+        }
+    }
+}
+
+/// A `Downloader` that forwards every request and records the responses to a directory.
+///
+/// Recordings use the `FixtureStore` layout, keyed by request fingerprint, so a
+/// `ReplayDownloader` can serve them later. Only responses with a status below
+/// 400 are recorded unless `record_error_statuses` is enabled; failed downloads
+/// are never recorded. A failure to write a recording is logged and the
+/// response is still returned.
+///
+/// ```rust,ignore
+/// let recorder = RecordingDownloader::new("tests/fixtures", ReqwestClientDownloader::new())
+///     .max_body_bytes(1024 * 1024);
+/// ```
+pub struct RecordingDownloader<D: Downloader> {
+    store: FixtureStore,
+    inner: D,
+    record_error_statuses: bool,
+    max_body_bytes: Option<usize>,
+}
+
+impl<D: Downloader> RecordingDownloader<D> {
+    /// Creates a downloader recording the responses of `inner` to `dir`.
+    pub fn new(dir: impl Into<PathBuf>, inner: D) -> Self {
+        RecordingDownloader {
+            store: FixtureStore::new(dir),
+            inner,
+            record_error_statuses: false,
+            max_body_bytes: None,
+        }
+    }
+
+    /// Also records `4xx` and `5xx` responses. Disabled by default.
+    pub fn record_error_statuses(mut self, enabled: bool) -> Self {
+        self.record_error_statuses = enabled;
+        self
+    }
+
+    /// Skips recording responses whose body is larger than `limit` bytes.
+    pub fn max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = Some(limit);
+        self
+    }
+
+    /// Returns the fixture store recordings are written to.
+    pub fn store(&self) -> &FixtureStore {
+        &self.store
+    }
+
+    /// Returns the wrapped downloader.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    fn should_record(&self, response: &Response) -> bool {
+        if response.status.as_u16() >= 400 && !self.record_error_statuses {
+            return false;
+        }
+        self.max_body_bytes
+            .is_none_or(|limit| response.body.len() <= limit)
+    }
+}
+
+#[async_trait]
+impl<D: Downloader> Downloader for RecordingDownloader<D> {
+    type Client = D::Client;
+
+    async fn download(&self, request: Request) -> Result<Response, SpiderError> {
+        let fingerprint = self.inner.fingerprint(&request);
+        let response = self.inner.download(request).await?;
+        if !self.should_record(&response) {
+            debug!(
+                "Not recording {} (status {}, {} body bytes)",
+                response.url,
+                response.status,
+                response.body.len()
+            );
+            return Ok(response);
+        }
+        if let Err(e) = self.store.save(&fingerprint, &response).await {
+            warn!("Failed to record {}: {}", response.url, e);
+        }
+        Ok(response)
+    }
+
+    fn client(&self) -> &Self::Client {
+        self.inner.client()
+    }
+
+    fn fingerprint(&self, request: &Request) -> String {
+        self.inner.fingerprint(request)
+    }
+}
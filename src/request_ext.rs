@@ -0,0 +1,118 @@
+//! Convenience methods on `Request`.
+//!
+//! `Request` is defined in `spider-util`, so downloader-level helpers are
+//! provided through the `RequestExt` extension trait.
+
+use bytes::Bytes;
+use serde::Serialize;
+use serde_json::Value;
+use spider_util::error::SpiderError;
+use spider_util::request::{Body, Request};
+
+/// Meta key holding structured query parameters as an array of `[key, value]` pairs.
+pub const META_QUERY: &str = "query";
+
+/// Extension methods for `Request`.
+pub trait RequestExt: Sized {
+    /// Attaches query parameters to be merged into the URL when downloading.
+    ///
+    /// `params` may be a struct, a map or a sequence of pairs, serialized with
+    /// `serde_urlencoded` so values are encoded correctly. When the request is
+    /// sent, keys present in `params` replace any parameters with the same key
+    /// already in the URL; other URL parameters are kept, and the new pairs are
+    /// appended in order. Calling this again replaces the previously attached
+    /// parameters.
+    fn with_query<T: Serialize + ?Sized>(self, params: &T) -> Result<Self, SpiderError>;
+
+    /// Gives the request an explicit zero-length body, sent with `Content-Length: 0`.
+    ///
+    /// Some APIs reject a `POST` without a `Content-Length`, which a request
+    /// with no body (`body: None`) may be sent without. `Body` has no empty
+    /// variant, so the empty body is an empty `Body::Bytes`; the downloader
+    /// sends the header for any empty `Body::Bytes`.
+    fn with_empty_body(self) -> Self;
+}
+
+impl RequestExt for Request {
+    fn with_query<T: Serialize + ?Sized>(self, params: &T) -> Result<Self, SpiderError> {
+        let encoded = serde_urlencoded::to_string(params)
+            .map_err(|e| SpiderError::GeneralError(format!("Failed to encode query: {}", e)))?;
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(&encoded)
+            .map_err(|e| SpiderError::GeneralError(format!("Failed to encode query: {}", e)))?;
+        let pairs = pairs
+            .into_iter()
+            .map(|(k, v)| Value::Array(vec![Value::String(k), Value::String(v)]))
+            .collect();
+        self.meta.insert(META_QUERY.into(), Value::Array(pairs));
+        Ok(self)
+    }
+
+    fn with_empty_body(mut self) -> Self {
+        self.body = Some(Body::Bytes(Bytes::new()));
+        self
+    }
+}
+
+/// Merges the query pairs stored under `META_QUERY` into `request.url`.
+///
+/// With `verbatim`, the URL's existing parameters keep their exact spelling
+/// instead of being decoded and re-encoded.
+pub(crate) fn apply_query(request: &mut Request, verbatim: bool) {
+    let pairs: Vec<(String, String)> = match request.meta.get(META_QUERY) {
+        Some(value) => value
+            .as_array()
+            .map(|pairs| {
+                pairs
+                    .iter()
+                    .filter_map(|pair| {
+                        let key = pair.get(0)?.as_str()?;
+                        let value = pair.get(1)?.as_str()?;
+                        Some((key.to_string(), value.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => return,
+    };
+    if pairs.is_empty() {
+        return;
+    }
+
+    if verbatim {
+        let mut query: Vec<String> = request
+            .url
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .filter(|segment| !segment.is_empty())
+            .filter(|segment| {
+                let raw_key = segment.split('=').next().unwrap_or("");
+                let (key, _) = url::form_urlencoded::parse(raw_key.as_bytes())
+                    .next()
+                    .unwrap_or_default();
+                !pairs.iter().any(|(k, _)| *k == key)
+            })
+            .map(str::to_string)
+            .collect();
+        query.push(
+            url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&pairs)
+                .finish(),
+        );
+        request.url.set_query(Some(&query.join("&")));
+        return;
+    }
+
+    let kept: Vec<(String, String)> = request
+        .url
+        .query_pairs()
+        .filter(|(key, _)| !pairs.iter().any(|(k, _)| k == key))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    request
+        .url
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(pairs);
+}
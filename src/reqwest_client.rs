@@ -6,43 +6,309 @@
 //! and converting the received HTTP responses into `Response` objects suitable
 //! for further processing by the crawler.
 //!
-//! This downloader handles various HTTP methods, request bodies (JSON, form data, bytes),
-//! and integrates with the framework's error handling.
+//! This downloader handles any HTTP method, including `PATCH`, `DELETE` and
+//! extension methods, with request bodies (JSON, form data, bytes) attached
+//! regardless of method, and integrates with the framework's error handling.
+//!
+//! Protocol upgrades such as WebSocket are not supported: a `101 Switching
+//! Protocols` response fails with `DownloadError::UnexpectedUpgrade` without
+//! reading a body.
 
-use crate::{Downloader, SimpleHttpClient};
+use crate::clock::{Clock, SystemClock};
+use crate::cost::RequestCost;
+use crate::decompress::{DecoderChain, codings_to_decode, supported_encodings};
+use crate::digest_auth::DigestAuth;
+use crate::dns::{AddressFamily, FamilyResolver};
+use crate::error::{AttemptError, AttemptErrorKind, DownloadError, DownloadFailure};
+use crate::host_clients::{HostClients, IdleEvictionMode};
+use crate::meta::{
+    META_COOKIES, META_DECODED_BYTES, META_DISABLE_POOL, META_DOWNGRADED, META_DOWNLOADER_CONFIG,
+    META_DRY_RUN, META_ENCODED_BYTES, META_FETCHED_AT, META_FINGERPRINT, META_IDEMPOTENCY_KEY,
+    META_MALFORMED_REDIRECT, META_ORIGINAL_CONTENT_ENCODING, META_ORIGINAL_URL, META_PRIORITY,
+    META_RATE_LIMIT, META_REDIRECT_CHAIN, META_REMOTE_ADDR, META_REMOVE_HEADERS, META_REQUEST_ID,
+    META_SAMPLED_OUT, META_SENT_REQUEST, META_SOFT_404, META_TAG, META_TRUNCATED,
+};
+use crate::meta_refresh::find_meta_refresh;
+use crate::metrics::{
+    BodySizeSummary, BodySizeTracker, DEFAULT_STATS_TAG, DownloadStats, METRIC_BODY_BYTES,
+    METRIC_HOST_CLIENTS, MetricsSink, StatsTracker, TagStats,
+};
+use crate::oauth2::OAuth2Provider;
+#[cfg(feature = "rustls-tls")]
+use crate::probe::{self, ProbeResult};
+use crate::proxy::{
+    EnvProxy, ProxyConfig, ProxyHealth, ProxyPool, ProxyRules, UrlMatcher, glob_match, is_bypassed,
+    redact_proxy_url,
+};
+use crate::rate_limit::RateLimitInfo;
+use crate::request_ext::apply_query;
+use crate::retry::{RetryAction, parse_retry_after};
+use crate::soft404::{SOFT_404_PREFIX_LEN, SoftNotFoundDetector};
+use crate::stream::{BodyStream, StreamingResponse};
+use crate::tls::{TlsConfig, TlsVersion};
+use crate::upload::{UploadBody, UploadProgress};
+use crate::url_normalize::normalize_url;
+use crate::validate::ResponseValidator;
+use crate::{
+    AcceptPreset, Downloader, HEALTH_CHECK_TIMEOUT, RequestSigner, RetryPolicy, SimpleHttpClient,
+};
 use async_trait::async_trait;
-use bytes::Bytes;
-use http::StatusCode;
-use reqwest::{Client, Proxy};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::{Bytes, BytesMut};
+use futures_util::stream::{self, Stream, StreamExt};
+use http::header::{
+    ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+    EXPECT, LOCATION, PROXY_AUTHORIZATION, RETRY_AFTER, TRANSFER_ENCODING, UPGRADE, USER_AGENT,
+};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Version};
+use log::{debug, info, warn};
+use percent_encoding::percent_decode_str;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::{Client, ClientBuilder, Proxy, RequestBuilder};
+use serde_json::Value;
 use spider_util::error::SpiderError;
 use spider_util::request::{Body, Request};
 use spider_util::response::Response;
-use std::time::Duration;
-use log::info;
-use tokio::sync::RwLock;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore, mpsc, watch};
+use url::Url;
+use uuid::Uuid;
+
+/// Header carrying the idempotency key of non-idempotent requests.
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// RFC 9218 extensible priority header.
+const PRIORITY: &str = "priority";
+/// Largest decoded body `SimpleHttpClient::get_text` accepts from a compressed response.
+const MAX_TEXT_DECODED_BYTES: usize = 64 * 1024 * 1024;
 
 #[async_trait]
 impl SimpleHttpClient for Client {
+    /// Fetches `url`, decoding the body according to its `Content-Encoding`.
+    ///
+    /// Every coding this build supports is advertised in `Accept-Encoding`. A
+    /// body using a coding that isn't supported is returned undecoded. The body
+    /// is decoded as it arrives, failing with `DownloadError::DecompressionBomb`
+    /// once it decodes to more than `MAX_TEXT_DECODED_BYTES`.
+    ///
+    /// Only transient transport failures are reported as
+    /// `SpiderError::ReqwestError`; an invalid URL, a TLS failure or a
+    /// redirect loop becomes a `SpiderError::GeneralError`, so
+    /// `get_text_retry` doesn't retry them.
     async fn get_text(
         &self,
         url: &str,
         timeout: Duration,
     ) -> Result<(StatusCode, Bytes), SpiderError> {
-        let resp = self.get(url).timeout(timeout).send().await?;
+        let mut resp = self
+            .get(url)
+            .header(ACCEPT_ENCODING, supported_encodings().join(", "))
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(text_error)?;
+        let status = resp.status();
+        let codings = codings_to_decode(resp.headers());
+        let Some(mut decoder) = DecoderChain::new(&codings, Some(MAX_TEXT_DECODED_BYTES)) else {
+            // Nothing to decode: hand back the body without copying it.
+            return Ok((status, resp.bytes().await.map_err(text_error)?));
+        };
+        let mut encoded_bytes = 0;
+        while let Some(chunk) = resp.chunk().await.map_err(text_error)? {
+            encoded_bytes += chunk.len();
+            decoder.write(&chunk)?;
+        }
+        if encoded_bytes == 0 {
+            return Ok((status, Bytes::new()));
+        }
+        Ok((status, Bytes::from(decoder.finish()?)))
+    }
+
+    /// Posts `form`, returning the body as received without decoding it.
+    async fn post_form(
+        &self,
+        url: &str,
+        form: &[(&str, &str)],
+        headers: HeaderMap,
+        timeout: Duration,
+    ) -> Result<(StatusCode, Bytes), SpiderError> {
+        let resp = self
+            .post(url)
+            .headers(headers)
+            .form(form)
+            .timeout(timeout)
+            .send()
+            .await?;
         let status = resp.status();
-        let body = resp.bytes().await?;
-        Ok((status, body))
+        Ok((status, resp.bytes().await?))
+    }
+
+    /// Sends a `HEAD` request, falling back to `GET` if the server doesn't allow `HEAD`.
+    async fn health_check(&self, url: &str) -> Result<StatusCode, SpiderError> {
+        let status = self
+            .head(url)
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await?
+            .status();
+        if status != StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(status);
+        }
+        Ok(self
+            .get(url)
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await?
+            .status())
     }
 }
 
+/// Converts a `get_text` failure, keeping only transient ones as `SpiderError::ReqwestError`.
+fn text_error(err: reqwest::Error) -> SpiderError {
+    let err = FetchError::Transport(err);
+    if is_transient(&err) && !is_tls_error(&err) {
+        return err.into();
+    }
+    SpiderError::GeneralError(err.to_string())
+}
+
 /// Concrete implementation of Downloader using reqwest client
+///
+/// Cloning is cheap, and clones share the base client, the per-host and
+/// proxied clients and their connection pools, the per-host concurrency
+/// limits, the open connection limit, the request statistics and `abort_all`.
+/// Clone the downloader into tasks instead of building one per task. Builder
+/// methods called on a clone only change that clone's settings: those that
+/// change how clients are built (`min_tls_version`, `max_tls_version`,
+/// `disable_pool_for_hosts`, `address_family`, `connect_to`, `pin_host`,
+/// `clear_pins`, `tls_sni`, `danger_accept_invalid_hostnames` and
+/// `tls_for_host`) also give the clone its own per-host and proxied clients,
+/// so it stops sharing their pools.
+#[derive(Clone)]
 pub struct ReqwestClientDownloader {
     client: Client,
     timeout: Duration,
     /// Per-host connection pools for better resource management
-    host_clients: Arc<RwLock<HashMap<String, Client>>>,
+    host_clients: Arc<HostClients>,
+    /// Whether separate per-host clients are built, or the base client is shared
+    per_host_clients: bool,
+    /// Canonicalize request URLs before fingerprinting and sending
+    normalize_urls: bool,
+    /// Sort query parameters as part of URL normalization
+    sort_query_params: bool,
+    /// `Accept` header applied to requests that don't set one
+    accept: Option<HeaderValue>,
+    /// Retry policy used unless a request overrides it through meta
+    retry_policy: RetryPolicy,
+    /// URL-pattern based proxy selection
+    proxy_rules: ProxyRules,
+    /// Proxies read from the environment, when enabled
+    env_proxy: Option<EnvProxy>,
+    /// Optional sink receiving download metrics
+    metrics: Option<Arc<dyn MetricsSink>>,
+    /// Rolling summary of response body sizes
+    body_sizes: Arc<BodySizeTracker>,
+    /// Time source for all delays
+    clock: Arc<dyn Clock>,
+    /// Attach an `Idempotency-Key` header to non-idempotent requests
+    idempotency_keys: bool,
+    /// Decode compressed bodies according to `Content-Encoding`
+    decompress: bool,
+    /// Upper bound on the decoded size of a compressed body
+    max_decompressed_bytes: Option<usize>,
+    /// Header carrying the correlation ID, when propagation is enabled
+    request_id_header: Option<HeaderName>,
+    /// Follow `<meta http-equiv="refresh">` redirects in HTML bodies
+    follow_meta_refresh: bool,
+    /// Maximum number of meta-refresh redirects followed per request
+    max_meta_refresh_hops: usize,
+    /// Signs each request after all headers are set
+    signer: Option<Arc<dyn RequestSigner>>,
+    /// Progress callback for streamed uploads
+    upload_progress: Option<Arc<dyn Fn(UploadProgress) + Send + Sync>>,
+    /// Hosts whose clients keep no idle connections
+    no_pool_hosts: HashSet<String>,
+    /// Log requests instead of sending them
+    dry_run: bool,
+    /// Default limit on simultaneous downloads from one host
+    max_concurrent_per_host: Option<usize>,
+    /// Per-host overrides of the concurrency limit
+    host_concurrency: HashMap<String, usize>,
+    /// Semaphores enforcing the per-host concurrency limits
+    host_semaphores: Arc<RwLock<HashMap<String, Arc<Semaphore>>>>,
+    /// Checks run on every downloaded response, with whether a rejection is retried
+    validators: Vec<(Arc<dyn ResponseValidator>, bool)>,
+    /// IP address families used for connections
+    address_family: AddressFamily,
+    /// Extra tries and wait for attempts that could not get a connection
+    pool_acquire_retry: Option<(u32, Duration)>,
+    /// Fraction of requests actually sent
+    sample_rate: f64,
+    /// Random source deciding which requests are sampled
+    sampler: Arc<Mutex<StdRng>>,
+    /// Total and per-host request counters
+    stats: Arc<StatsTracker>,
+    /// Send `Expect: 100-continue` with request bodies
+    expect_continue: bool,
+    /// Fixed addresses to connect to for given hosts, bypassing DNS
+    connect_to: HashMap<String, SocketAddr>,
+    /// Bumped by `abort_all` to cancel every in-flight download
+    abort: Arc<watch::Sender<u64>>,
+    /// Instant after which downloads are refused and in-flight ones cancelled
+    global_deadline: Option<Instant>,
+    /// Flags successful responses that are really missing pages
+    soft_404_detector: Option<Arc<dyn SoftNotFoundDetector>>,
+    /// Send request URLs without any rewriting by the downloader
+    verbatim_urls: bool,
+    /// Send the server name indication in TLS handshakes
+    tls_sni: bool,
+    /// Accept certificates whose names don't match the host, still checking the chain
+    accept_invalid_hostnames: bool,
+    /// Canned responses served instead of the network, checked in order
+    stubs: Vec<Stub>,
+    /// Record the final form of each request in `Response.meta`
+    record_sent_requests: bool,
+    /// Body chunks read ahead of a `download_stream` consumer
+    stream_buffer_chunks: usize,
+    /// TLS overrides for individual hosts
+    host_tls: HashMap<String, TlsConfig>,
+    /// Retry over plain HTTP after a TLS handshake failure
+    https_downgrade: bool,
+    /// Hosts never downgraded to plain HTTP
+    https_only_hosts: HashSet<String>,
+    /// Pre-resolved addresses used for given hosts instead of DNS
+    pinned_hosts: HashMap<String, Vec<SocketAddr>>,
+    /// Open a fresh connection for every request to every host
+    no_connection_reuse: bool,
+    /// Oldest TLS version accepted
+    min_tls_version: TlsVersion,
+    /// Newest TLS version offered, if capped
+    max_tls_version: Option<TlsVersion>,
+    /// Limit on requests holding a connection, and the semaphore enforcing it
+    open_connections: Option<(usize, Arc<Semaphore>)>,
+    /// Proxies requests are spread over when no rule or meta picks one
+    proxy_pool: Option<Arc<ProxyPool>>,
+    /// Largest body accepted as received, before decompression
+    max_body_bytes: Option<usize>,
+    /// Limit on reading a body once its headers arrived
+    body_read_timeout: Option<Duration>,
+    /// How long a per-host client may stay unused before it is evicted
+    host_client_idle_ttl: Option<Duration>,
+    /// How often idle per-host clients are swept
+    idle_eviction_interval: Duration,
+    /// Whether idle clients are swept on lookup or by a background task
+    idle_eviction_mode: IdleEvictionMode,
+    /// Post-processing applied in order to every downloaded response
+    response_mappers: Vec<Arc<ResponseMapper>>,
+    /// Credentials answering `Digest` challenges, and the challenges seen
+    digest_auth: Option<Arc<DigestAuth>>,
+    /// Source of the bearer token sent with every request
+    oauth2: Option<Arc<OAuth2Provider>>,
 }
 
 #[async_trait]
@@ -54,79 +320,553 @@ impl Downloader for ReqwestClientDownloader {
         &self.client
     }
 
+    /// Downloads `request`; see `try_download` for the error kept structured.
     async fn download(&self, request: Request) -> Result<Response, SpiderError> {
-        info!(
-            "Downloading {} (fingerprint: {})",
-            request.url,
-            request.fingerprint()
-        );
+        Ok(self.try_download(request).await?)
+    }
+
+    /// Sends the request and returns as soon as the headers arrive.
+    ///
+    /// The body is exposed as a stream of chunks instead of being buffered, and
+    /// is neither retried nor decompressed. Reading follows the consumer: by
+    /// default the connection is only read when the stream is polled, so a slow
+    /// consumer makes the server slow down instead of growing a buffer. See
+    /// `stream_buffer_chunks` to read ahead by a bounded amount.
+    async fn download_stream(&self, request: Request) -> Result<StreamingResponse, SpiderError> {
+        let (mut request, client, _fingerprint, _proxy) = self.prepare(request).await?;
+        self.authorize(&mut request).await?;
+        let connection = self.connection_permit()?;
+        let res = build_request(
+            &client,
+            &request.method,
+            &request.url,
+            &request.headers,
+            request.body.as_ref(),
+        )
+        .send()
+        .await?;
+        check_upgrade(&res)?;
 
-        let Request {
+        let url = res.url().clone();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let chunks = res.bytes_stream().map(move |chunk| {
+            // The connection slot is released when the body stream is dropped.
+            let _connection = &connection;
+            chunk.map_err(SpiderError::from)
+        });
+        let chunks: BodyStream = match self.body_read_timeout {
+            Some(timeout) => chunk_timeout(chunks, timeout, self.clock.clone(), url.clone()),
+            None => Box::pin(chunks),
+        };
+        let body: BodyStream = if self.stream_buffer_chunks == 0 {
+            chunks
+        } else {
+            read_ahead(chunks, self.stream_buffer_chunks)
+        };
+
+        Ok(StreamingResponse {
             url,
-            method,
+            status,
             headers,
+            request_url: request.url,
             body,
-            meta,
-            ..
-        } = request;
+        })
+    }
 
-        // Get host-specific client if available, otherwise use default
-        let host = url.host_str().unwrap_or("").to_string();
-        // Convert DashMap to HashMap for the host client creation
-        let meta_hashmap: std::collections::HashMap<String, serde_json::Value> = 
-            meta.iter().map(|entry| (entry.key().clone().into_owned(), entry.value().clone())).collect();
-        let mut client_to_use = self.get_or_create_host_client(&host, &meta_hashmap).await;
+    fn supports_true_streaming(&self) -> bool {
+        true
+    }
+
+    /// Fingerprints `request` as `download` does, after the URL rewrites of `normalize_urls` and `verbatim_urls`.
+    fn fingerprint(&self, request: &Request) -> String {
+        let mut request = request.clone();
+        take_url_credentials(&mut request);
+        self.rewrite_url(&mut request);
+        request.fingerprint()
+    }
 
-        if let Some(proxy_val) = meta.get("proxy")
-            && let Some(proxy_str) = proxy_val.as_str()
+    /// Rejects URLs that aren't `http`/`https` or lack a host, and everything once the global deadline has passed.
+    fn would_allow(&self, url: &Url) -> Result<(), SpiderError> {
+        if self
+            .global_deadline
+            .is_some_and(|deadline| self.clock.now() >= deadline)
         {
-            match Proxy::all(proxy_str) {
-                Ok(proxy) => {
-                    let new_client = Client::builder()
-                        .timeout(self.timeout)
-                        .proxy(proxy)
-                        .build()
-                        .map_err(|e| SpiderError::ReqwestError(e.into()))?;
-                    client_to_use = new_client;
-                }
-                Err(e) => {
-                    return Err(SpiderError::ReqwestError(e.into()));
+            return Err(DownloadError::DeadlineExceeded.into());
+        }
+        Ok(check_url(url)?)
+    }
+
+    /// Estimates cost from the proxy the request would use and past downloads.
+    ///
+    /// Proxied requests weigh twice as much as direct ones. The expected size
+    /// is the median of recent body sizes, and the failure rate is the share of
+    /// failed requests to the same host so far; a host with failures weighs
+    /// proportionally more.
+    fn estimated_cost(&self, request: &Request) -> RequestCost {
+        // Not `select_proxy`, which would advance the pool's rotation.
+        let uses_proxy = request.meta.get("proxy").is_some()
+            || self.proxy_rules.select(&request.url).is_some()
+            || self.pool_applies(&request.url)
+            || self
+                .env_proxy
+                .as_ref()
+                .is_some_and(|env| env.select(&request.url).is_some());
+        let sizes = self.body_sizes.summary();
+        let expected_bytes = (sizes.count > 0).then_some(sizes.p50 as u64);
+        let counts = self.stats.host(request.url.host_str().unwrap_or(""));
+        let failure_rate = if counts.requests > 0 {
+            counts.failures as f64 / counts.requests as f64
+        } else {
+            0.0
+        };
+        let base = if uses_proxy { 2.0 } else { 1.0 };
+        RequestCost {
+            weight: base * (1.0 + failure_rate),
+            uses_proxy,
+            expected_bytes,
+            failure_rate,
+        }
+    }
+}
+
+/// The parts of a reqwest response the downloader keeps after one attempt.
+struct Fetched {
+    url: Url,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    /// On-wire body size, when the body was decompressed
+    encoded_bytes: Option<usize>,
+    /// `Content-Encoding` the body had before it was decompressed
+    content_encoding: Option<String>,
+    /// Address of the peer the response came from
+    remote_addr: Option<SocketAddr>,
+    /// HTTP version the response was received over
+    version: Version,
+}
+
+/// A response post-processing step, see `ReqwestClientDownloader::map_response`.
+type ResponseMapper = dyn Fn(Response) -> Result<Response, SpiderError> + Send + Sync;
+
+/// A canned response served for URLs matching a pattern.
+#[derive(Clone)]
+struct Stub {
+    pattern: String,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Why a single attempt failed.
+enum FetchError {
+    Transport(reqwest::Error),
+    Download(DownloadError),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        FetchError::Transport(err)
+    }
+}
+
+impl From<FetchError> for DownloadFailure {
+    fn from(err: FetchError) -> Self {
+        match err {
+            FetchError::Transport(e) => DownloadFailure::Other(e.into()),
+            FetchError::Download(e) => DownloadFailure::Download(e),
+        }
+    }
+}
+
+impl From<FetchError> for SpiderError {
+    fn from(err: FetchError) -> Self {
+        match err {
+            FetchError::Transport(e) => e.into(),
+            FetchError::Download(e) => e.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Transport(e) => e.fmt(f),
+            FetchError::Download(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Returns whether the response declares an HTML content type.
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_ascii_lowercase().contains("html"))
+}
+
+/// Builds the reqwest request for one attempt.
+///
+/// The body is attached whatever the method, so `PATCH`, `DELETE` with a body
+/// and extension methods behave like `POST`. JSON and form bodies also set the
+/// matching `Content-Type`. An empty `Body::Bytes` is an explicit empty body and
+/// always sends `Content-Length: 0`, unlike no body at all; a `Content-Length`
+/// the caller set is sent as the only value.
+fn build_request(
+    client: &Client,
+    method: &Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: Option<&Body>,
+) -> RequestBuilder {
+    let mut req_builder = client.request(method.clone(), url.clone());
+
+    if let Some(body_content) = body {
+        req_builder = match body_content {
+            Body::Json(json_val) => req_builder.json(json_val),
+            Body::Form(form_val) => {
+                let mut form_map = std::collections::HashMap::new();
+                for entry in form_val.iter() {
+                    form_map.insert(entry.key().clone(), entry.value().clone());
                 }
+                req_builder.form(&form_map)
             }
+            Body::Bytes(bytes_val) => req_builder.body(bytes_val.clone()),
+        };
+    }
+
+    let mut headers = headers.clone();
+    if let Some(Body::Bytes(bytes_val)) = body
+        && bytes_val.is_empty()
+    {
+        headers
+            .entry(CONTENT_LENGTH)
+            .or_insert(HeaderValue::from_static("0"));
+    }
+    req_builder.headers(headers)
+}
+
+/// Moves `user:pass@` credentials from the request URL into an `Authorization: Basic` header.
+///
+/// An `Authorization` header already set on the request takes precedence; the
+/// credentials are stripped from the URL either way.
+fn take_url_credentials(request: &mut Request) {
+    let url = &mut request.url;
+    if url.username().is_empty() && url.password().is_none() {
+        return;
+    }
+    let username = percent_decode_str(url.username())
+        .decode_utf8_lossy()
+        .into_owned();
+    let password = url
+        .password()
+        .map(|p| percent_decode_str(p).decode_utf8_lossy().into_owned())
+        .unwrap_or_default();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+
+    if !request.headers.contains_key(AUTHORIZATION) {
+        let credentials = BASE64.encode(format!("{}:{}", username, password));
+        if let Ok(mut value) = HeaderValue::from_str(&format!("Basic {}", credentials)) {
+            value.set_sensitive(true);
+            request.headers.insert(AUTHORIZATION, value);
         }
+    }
+}
+
+/// Answers a request that was not sent with an empty `200 OK`, flagging `flag` in its meta.
+fn empty_response(request: Request, fingerprint: String, flag: &'static str) -> Response {
+    let Request { url, meta, .. } = request;
+    meta.insert(META_FINGERPRINT.into(), Value::String(fingerprint));
+    meta.insert(flag.into(), Value::Bool(true));
+    Response {
+        url: url.clone(),
+        status: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Bytes::new(),
+        request_url: url,
+        meta,
+        cached: false,
+    }
+}
 
-        let mut req_builder = client_to_use.request(method, url.clone());
+/// Fails on `101 Switching Protocols`, whose "body" would be another protocol's stream.
+fn check_upgrade(res: &reqwest::Response) -> Result<(), DownloadError> {
+    if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+        return Ok(());
+    }
+    let protocol = res
+        .headers()
+        .get(UPGRADE)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+        .unwrap_or_default();
+    Err(DownloadError::UnexpectedUpgrade { protocol })
+}
 
-        if let Some(body_content) = body {
-            req_builder = match body_content {
-                Body::Json(json_val) => req_builder.json(&json_val),
-                Body::Form(form_val) => {
-                    let mut form_map = std::collections::HashMap::new();
-                    for entry in form_val.iter() {
-                        form_map.insert(entry.key().clone(), entry.value().clone());
-                    }
-                    req_builder.form(&form_map)
-                }
-                Body::Bytes(bytes_val) => req_builder.body(bytes_val),
+/// Returns whether a response to `method` with `status` has no body to decode.
+///
+/// These carry none even when they advertise an encoding (a `HEAD` answer
+/// describes the `GET` one), and an empty stream isn't valid decoder input.
+fn is_bodiless(method: &Method, status: StatusCode) -> bool {
+    *method == Method::HEAD
+        || status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+}
+
+/// Returns whether a redirect status arrived without the `Location` it requires.
+///
+/// `300 Multiple Choices` and `304 Not Modified` don't need one.
+fn is_malformed_redirect(status: StatusCode, headers: &HeaderMap) -> bool {
+    matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) && !headers.contains_key(LOCATION)
+}
+
+/// Returns the header names listed in the request's `remove_headers` meta.
+fn removed_headers(request: &Request) -> Vec<String> {
+    request
+        .meta
+        .get(META_REMOVE_HEADERS)
+        .and_then(|v| {
+            v.as_array().map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Merges the cookies in the request's `cookies` meta into its `Cookie` header.
+///
+/// Cookies already in the header are kept unless a meta cookie has the same
+/// name, in which case the meta value wins. The downloader keeps no cookie jar,
+/// so these cookies never outlive the request. A jar on a client passed to
+/// `from_client` is not consulted for requests that carry a `Cookie` header.
+fn apply_cookies(request: &mut Request) {
+    let cookies: Vec<(String, String)> = request
+        .meta
+        .get(META_COOKIES)
+        .and_then(|v| {
+            v.as_object().map(|cookies| {
+                cookies
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                    .collect()
+            })
+        })
+        .unwrap_or_default();
+    if cookies.is_empty() {
+        return;
+    }
+
+    let existing = request
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .map(str::trim)
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or("");
+            !pair.is_empty() && !cookies.iter().any(|(n, _)| n == name)
+        })
+        .map(str::to_string);
+    let merged: Vec<String> = existing
+        .chain(
+            cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value)),
+        )
+        .collect();
+    if let Ok(value) = HeaderValue::from_str(&merged.join("; ")) {
+        request.headers.insert(COOKIE, value);
+    }
+}
+
+/// Sends the request's `priority` meta as an RFC 9218 `Priority: u=N` header.
+///
+/// reqwest gives no control over HTTP/2 stream weights, so the priority can
+/// only be expressed as this header, which servers supporting extensible
+/// priorities honor over HTTP/1.1, HTTP/2 and HTTP/3 alike; others ignore it.
+/// Values are clamped to 0..=7, and a `Priority` header already set wins.
+fn apply_priority(request: &mut Request) {
+    let Some(urgency) = request.meta.get(META_PRIORITY).and_then(|v| v.as_u64()) else {
+        return;
+    };
+    if request.headers.contains_key(PRIORITY) {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("u={}", urgency.min(7))) {
+        request.headers.insert(PRIORITY, value);
+    }
+}
+
+/// Reads `chunks` on a background task into a channel holding at most `capacity` items.
+fn read_ahead<S>(chunks: S, capacity: usize) -> BodyStream
+where
+    S: Stream<Item = Result<Bytes, SpiderError>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(async move {
+        let mut chunks = Box::pin(chunks);
+        while let Some(chunk) = chunks.next().await {
+            // The receiver is gone once the consumer drops the stream.
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+    Box::pin(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    }))
+}
+
+/// Ends `chunks` with `BodyReadTimeout` when no chunk arrives within `timeout`.
+fn chunk_timeout<S>(chunks: S, timeout: Duration, clock: Arc<dyn Clock>, url: Url) -> BodyStream
+where
+    S: Stream<Item = Result<Bytes, SpiderError>> + Send + 'static,
+{
+    Box::pin(stream::unfold(Some(Box::pin(chunks)), move |chunks| {
+        let clock = clock.clone();
+        let url = url.clone();
+        async move {
+            let mut chunks = chunks?;
+            let next = tokio::select! {
+                chunk = chunks.next() => Some(chunk),
+                _ = clock.sleep(timeout) => None,
             };
+            match next {
+                Some(chunk) => chunk.map(|chunk| (chunk, Some(chunks))),
+                None => {
+                    let error = DownloadError::BodyReadTimeout {
+                        url: url.to_string(),
+                        timeout,
+                    };
+                    Some((Err(error.into()), None))
+                }
+            }
+        }
+    }))
+}
+
+/// Describes the method, URL and headers of a prepared request, redacting credentials.
+fn describe_sent_request(request: &Request) -> Value {
+    let mut headers = serde_json::Map::new();
+    for (name, value) in &request.headers {
+        let redacted = value.is_sensitive()
+            || name == AUTHORIZATION
+            || name == PROXY_AUTHORIZATION
+            || name == COOKIE;
+        let value = if redacted {
+            "[redacted]".to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        match headers.get_mut(name.as_str()) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                headers.insert(name.as_str().to_string(), Value::String(value));
+            }
         }
+    }
+    serde_json::json!({
+        "method": request.method.as_str(),
+        "url": request.url.as_str(),
+        "headers": headers,
+    })
+}
 
-        let res = req_builder.headers(headers).send().await?;
+/// Summarizes a request body for log lines.
+fn describe_body(body: Option<&Body>) -> String {
+    match body {
+        None => "none".to_string(),
+        Some(Body::Json(value)) => format!("JSON, {} bytes", value.to_string().len()),
+        Some(Body::Form(form)) => format!("form, {} fields", form.len()),
+        Some(Body::Bytes(bytes)) => format!("{} bytes", bytes.len()),
+    }
+}
 
-        let response_url = res.url().clone();
-        let status = res.status();
-        let response_headers = res.headers().clone();
-        let response_body = res.bytes().await?;
+/// Classifies a failed attempt for the attempt history.
+fn attempt_error_kind(err: &FetchError) -> AttemptErrorKind {
+    match err {
+        FetchError::Transport(e) if e.is_timeout() => AttemptErrorKind::Timeout,
+        FetchError::Transport(e) if e.is_connect() => AttemptErrorKind::Connect,
+        FetchError::Transport(e) if e.is_body() => AttemptErrorKind::Body,
+        FetchError::Download(DownloadError::BodyReadTimeout { .. }) => AttemptErrorKind::Timeout,
+        _ => AttemptErrorKind::Other,
+    }
+}
 
-        Ok(Response {
-            url: response_url,
-            status,
-            headers: response_headers,
-            body: response_body,
-            request_url: url,
-            meta,
-            cached: false,
-        })
+/// Returns all values of header `name` joined with `, `, or `None` if it is absent.
+fn joined_header(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    let values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// Parses a URL given as a string to a convenience method.
+fn parse_url(url: &str) -> Result<Url, SpiderError> {
+    Url::parse(url).map_err(|e| SpiderError::GeneralError(format!("Invalid URL {}: {}", url, e)))
+}
+
+/// Checks that `url` is something the downloader can fetch.
+fn check_url(url: &Url) -> Result<(), DownloadError> {
+    let reason = if !matches!(url.scheme(), "http" | "https") {
+        format!("unsupported scheme {:?}", url.scheme())
+    } else if url.host().is_none() {
+        "missing host".to_string()
+    } else {
+        return Ok(());
+    };
+    Err(DownloadError::UnsupportedUrl {
+        url: url.to_string(),
+        reason,
+    })
+}
+
+/// Returns whether a failed attempt is a TLS handshake or certificate failure.
+///
+/// reqwest doesn't classify TLS errors, so the connect error's sources are
+/// searched for TLS-related messages.
+fn is_tls_error(err: &FetchError) -> bool {
+    match err {
+        FetchError::Transport(e) => {
+            e.is_connect() && error_chain_contains(e, &["tls", "ssl", "certificate", "handshake"])
+        }
+        FetchError::Download(DownloadError::TlsVersionTooLow { .. }) => true,
+        FetchError::Download(_) => false,
+    }
+}
+
+/// Returns whether a source of `err` mentions any of the lowercase `needles`.
+fn error_chain_contains(err: &reqwest::Error, needles: &[&str]) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        let message = err.to_string().to_ascii_lowercase();
+        if needles.iter().any(|needle| message.contains(needle)) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Returns whether a failed attempt is worth retrying.
+fn is_transient(err: &FetchError) -> bool {
+    match err {
+        FetchError::Transport(e) => {
+            e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+        }
+        FetchError::Download(DownloadError::BodyReadTimeout { .. }) => true,
+        FetchError::Download(_) => false,
     }
 }
 
@@ -138,52 +878,2251 @@ impl ReqwestClientDownloader {
 
     /// Creates a new `ReqwestClientDownloader` with a specified request timeout.
     pub fn new_with_timeout(timeout: Duration) -> Self {
-        let base_client = Client::builder()
-            .timeout(timeout)
-            .pool_max_idle_per_host(200)
-            .pool_idle_timeout(Duration::from_secs(120))
-            .tcp_keepalive(Duration::from_secs(60))
-            .connect_timeout(Duration::from_secs(10))
-            .build()
-            .unwrap();
-            
+        let base_client = build_base_client(timeout, 200, TlsVersion::Tls1_2, None);
+        Self::with_base_client(base_client, timeout, true)
+    }
+
+    /// Creates a new `ReqwestClientDownloader` that reuses an externally-built client.
+    ///
+    /// The injected client is used for every host instead of building per-host
+    /// clients, so its TLS, resolver and pool settings are never lost. `timeout`
+    /// is still used when a proxied client has to be built for a request.
+    pub fn from_client(client: Client, timeout: Duration) -> Self {
+        Self::with_base_client(client, timeout, false)
+    }
+
+    fn with_base_client(client: Client, timeout: Duration, per_host_clients: bool) -> Self {
         ReqwestClientDownloader {
-            client: base_client.clone(),
+            client,
             timeout,
-            host_clients: Arc::new(RwLock::new(HashMap::new())),
+            host_clients: Arc::new(HostClients::new()),
+            per_host_clients,
+            normalize_urls: false,
+            sort_query_params: false,
+            accept: None,
+            retry_policy: RetryPolicy::none(),
+            proxy_rules: ProxyRules::default(),
+            env_proxy: None,
+            metrics: None,
+            body_sizes: Arc::new(BodySizeTracker::new()),
+            clock: Arc::new(SystemClock),
+            idempotency_keys: false,
+            decompress: false,
+            max_decompressed_bytes: None,
+            request_id_header: None,
+            follow_meta_refresh: false,
+            max_meta_refresh_hops: 5,
+            signer: None,
+            upload_progress: None,
+            no_pool_hosts: HashSet::new(),
+            dry_run: false,
+            max_concurrent_per_host: None,
+            host_concurrency: HashMap::new(),
+            host_semaphores: Arc::new(RwLock::new(HashMap::new())),
+            validators: Vec::new(),
+            address_family: AddressFamily::Any,
+            pool_acquire_retry: None,
+            sample_rate: 1.0,
+            sampler: Arc::new(Mutex::new(StdRng::from_os_rng())),
+            stats: Arc::new(StatsTracker::default()),
+            expect_continue: false,
+            connect_to: HashMap::new(),
+            abort: Arc::new(watch::Sender::new(0)),
+            global_deadline: None,
+            soft_404_detector: None,
+            verbatim_urls: false,
+            tls_sni: true,
+            accept_invalid_hostnames: false,
+            stubs: Vec::new(),
+            record_sent_requests: false,
+            stream_buffer_chunks: 0,
+            host_tls: HashMap::new(),
+            https_downgrade: false,
+            https_only_hosts: HashSet::new(),
+            pinned_hosts: HashMap::new(),
+            no_connection_reuse: false,
+            min_tls_version: TlsVersion::Tls1_2,
+            max_tls_version: None,
+            open_connections: None,
+            proxy_pool: None,
+            max_body_bytes: None,
+            body_read_timeout: None,
+            host_client_idle_ttl: None,
+            idle_eviction_interval: Duration::from_secs(60),
+            idle_eviction_mode: IdleEvictionMode::Lazy,
+            response_mappers: Vec::new(),
+            digest_auth: None,
+            oauth2: None,
         }
     }
 
-    /// Gets or creates a host-specific client with optimized settings for that host
-    async fn get_or_create_host_client(&self, host: &str, _meta: &std::collections::HashMap<String, serde_json::Value>) -> Client {
+    /// Answers HTTP Digest challenges with `username` and `password`.
+    ///
+    /// A `401` carrying a `WWW-Authenticate: Digest` challenge is answered by
+    /// resending the request once with the computed `Authorization` header,
+    /// preferring `SHA-256` over `MD5` when both are offered. The challenge is
+    /// remembered per origin, so later requests answer it up front with the
+    /// next nonce count; a stale nonce costs one more `401`. Requests that
+    /// already carry an `Authorization` header are left alone. Clones of the
+    /// downloader share the remembered challenges.
+    pub fn with_digest_auth(mut self, username: &str, password: &str) -> Self {
+        self.digest_auth = Some(Arc::new(DigestAuth::new(username, password)));
+        self
+    }
+
+    /// Sends an `Authorization: Bearer` token obtained from `provider` with every request.
+    ///
+    /// Requests that already carry an `Authorization` header are left alone.
+    /// A `401` answer makes the provider drop the token it was sent with, so
+    /// the next request fetches a new one; the rejected request itself is not
+    /// resent. Failing to obtain a token fails the download with
+    /// `DownloadError::TokenRequestFailed`. Stubbed, dry-run and sampled-out
+    /// requests are answered without a token.
+    pub fn oauth2(mut self, provider: Arc<OAuth2Provider>) -> Self {
+        self.oauth2 = Some(provider);
+        self
+    }
+
+    /// Signs every request with `signer` right before it is sent.
+    ///
+    /// The signer runs after all headers added by the downloader, so it sees the
+    /// request in its final form.
+    pub fn signer(mut self, signer: Arc<dyn RequestSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Follows `<meta http-equiv="refresh">` redirects found in HTML responses.
+    ///
+    /// The first 16 KiB of successful HTML bodies are scanned for a refresh
+    /// target, which is fetched with `GET`. Followed URLs are recorded in
+    /// `Response.meta` under `redirect_chain`. Disabled by default.
+    ///
+    /// Like HTTP redirects, a refresh to another origin is sent without the
+    /// request's credentials and cookies, and with the client for its host.
+    pub fn follow_meta_refresh(mut self, enabled: bool) -> Self {
+        self.follow_meta_refresh = enabled;
+        self
+    }
+
+    /// Caps the number of meta-refresh redirects followed per request. Defaults to 5.
+    pub fn max_meta_refresh_hops(mut self, hops: usize) -> Self {
+        self.max_meta_refresh_hops = hops;
+        self
+    }
+
+    /// Propagates a correlation ID in the `header_name` header, e.g. `X-Request-Id`.
+    ///
+    /// The ID is taken from the `request_id` meta key, then from the header if the
+    /// request already sets it, and generated otherwise (a W3C trace context when
+    /// the header is `traceparent`, a UUID for any other header). It is echoed
+    /// in `Response.meta` under `request_id` and included in the download log line.
+    pub fn propagate_request_id(mut self, header_name: &str) -> Self {
+        match HeaderName::from_bytes(header_name.as_bytes()) {
+            Ok(name) => self.request_id_header = Some(name),
+            Err(_) => warn!("Ignoring invalid request ID header name {:?}", header_name),
+        }
+        self
+    }
+
+    /// Aborts decompression once the decoded body exceeds `limit` bytes.
+    ///
+    /// The limit is checked after every received chunk, so a small compressed
+    /// payload that inflates to gigabytes is rejected with
+    /// `DownloadError::DecompressionBomb` before it is fully decoded. Only applies
+    /// with `decompress(true)`. Unlimited by default.
+    pub fn max_decompressed_bytes(mut self, limit: usize) -> Self {
+        self.max_decompressed_bytes = Some(limit);
+        self
+    }
+
+    /// Fails a download whose body stalls after the headers arrived, with `DownloadError::BodyReadTimeout`.
+    ///
+    /// For buffered downloads, the whole body must be read within `timeout`
+    /// of the headers arriving. A timed out attempt is retried like other
+    /// timeouts. Streaming downloads can last indefinitely, so for them each
+    /// chunk must arrive within `timeout` of the previous one. The request
+    /// timeout still bounds the whole download. Unlimited by default.
+    pub fn body_read_timeout(mut self, timeout: Duration) -> Self {
+        self.body_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Rejects bodies larger than `limit` bytes as received, with `DownloadError::BodyTooLarge`.
+    ///
+    /// A `Content-Length` above the limit fails before the body is read;
+    /// otherwise the limit is checked after every received chunk. Compressed
+    /// bodies are measured before decoding, see `max_decompressed_bytes` for
+    /// their decoded size. Streaming downloads are not limited. Unlimited by
+    /// default.
+    pub fn max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = Some(limit);
+        self
+    }
+
+    /// Decodes compressed response bodies.
+    ///
+    /// `gzip` and `deflate` are always supported, `br` and `zstd` with the
+    /// `brotli` and `zstd` features. Sends an `Accept-Encoding` listing the
+    /// supported codings, preferring `zstd` then `br`, unless the request sets
+    /// one (e.g. `Accept-Encoding: br` to ask for brotli only), decodes
+    /// the body while it is read, and removes the now stale `Content-Encoding`,
+    /// `Transfer-Encoding` and `Content-Length` headers. Codings in both headers
+    /// are honored: transfer codings are removed first, then content codings,
+    /// each in reverse of the order listed. A body with any unsupported coding
+    /// is returned undecoded. The on-wire and decoded sizes are recorded
+    /// in `Response.meta` as `encoded_bytes` and `decoded_bytes`, and the removed
+    /// `Content-Encoding` as `original_content_encoding`. Disabled by default.
+    pub fn decompress(mut self, enabled: bool) -> Self {
+        self.decompress = enabled;
+        self
+    }
+
+    /// Attaches a UUID `Idempotency-Key` header to non-idempotent requests.
+    ///
+    /// Applies to methods such as `POST` and `PATCH`. The key is generated once per
+    /// request and reused for all of its retries, so compatible servers can
+    /// deduplicate them; an existing header is kept. The key is recorded in
+    /// `Response.meta` under `idempotency_key`. Disabled by default.
+    pub fn idempotency_keys(mut self, enabled: bool) -> Self {
+        self.idempotency_keys = enabled;
+        self
+    }
+
+    /// Replaces the clock used for retry backoff and other delays.
+    ///
+    /// Use `TokioClock` together with `tokio::time::pause()` to test timing
+    /// without waiting. Defaults to `SystemClock`.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sends download metrics, such as response body sizes, to `sink`.
+    pub fn metrics_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Returns the request counters, in total and per host.
+    ///
+    /// Counts requests sent by `download`; dry runs and sampled-out requests are
+    /// not counted.
+    pub fn stats(&self) -> DownloadStats {
+        self.stats.snapshot()
+    }
+
+    /// Returns the request counters per `tag` given in request meta.
+    ///
+    /// Counts the same requests as `stats`; requests without a string `tag` are
+    /// counted under `DEFAULT_STATS_TAG`. Tags are kept until `reset_stats`, so
+    /// use a bounded set of job names rather than per-request values.
+    pub fn stats_by_tag(&self) -> HashMap<String, TagStats> {
+        self.stats.by_tag()
+    }
+
+    /// Resets the request counters to zero, e.g. after each sampling period.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// Returns the count, total and recent p50/p95 of response body sizes.
+    pub fn body_size_summary(&self) -> BodySizeSummary {
+        self.body_sizes.summary()
+    }
+
+    /// Uses the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables.
+    ///
+    /// The variables are read when this is called. Environment proxies have the
+    /// lowest precedence: a `proxy` entry in request meta wins, then
+    /// `proxy_rules`, then `proxy_pool`, then the environment. `NO_PROXY` only
+    /// bypasses the environment proxies. Disabled by default, in which case the
+    /// environment is ignored entirely (clients built by this downloader never
+    /// pick up system proxies implicitly).
+    pub fn use_env_proxy(mut self, enabled: bool) -> Self {
+        self.env_proxy = enabled.then(EnvProxy::from_env);
+        self
+    }
+
+    /// Routes requests through a proxy chosen by URL pattern.
+    ///
+    /// Rules are tried in order and the first matching one wins; URLs matching no
+    /// rule connect directly. A `proxy` entry in a request's meta takes precedence
+    /// over these rules.
+    pub fn proxy_rules(mut self, rules: Vec<(UrlMatcher, ProxyConfig)>) -> Self {
+        self.proxy_rules.rules = rules;
+        self
+    }
+
+    /// Spreads requests over the proxies of `pool`.
+    ///
+    /// The pool is used for requests that neither set `proxy` in their meta
+    /// nor match a proxy rule, and takes precedence over environment proxies.
+    /// Hosts in `proxy_bypass` connect directly. Each request's outcome is fed
+    /// back to the pool: a transport error, `407 Proxy Authentication Required`,
+    /// `502 Bad Gateway` or `504 Gateway Timeout` counts as a failure of the
+    /// proxy, see `ProxyPool::evict_failing` and `proxy_health`. Clones of the
+    /// downloader share the pool.
+    pub fn proxy_pool(mut self, pool: ProxyPool) -> Self {
+        self.proxy_pool = Some(Arc::new(pool));
+        self
+    }
+
+    /// Returns the health of each proxy in the proxy pool, or nothing without a pool.
+    pub fn proxy_health(&self) -> Vec<ProxyHealth> {
+        self.proxy_pool
+            .as_ref()
+            .map(|pool| pool.health())
+            .unwrap_or_default()
+    }
+
+    /// Hosts that always bypass the proxy rules, using `NO_PROXY` syntax.
+    ///
+    /// Entries match as domain suffixes (`example.com` covers `api.example.com`),
+    /// may carry a `:port`, and `*` bypasses every host.
+    pub fn proxy_bypass(mut self, hosts: Vec<String>) -> Self {
+        self.proxy_rules.bypass = hosts;
+        self
+    }
+
+    /// Sets the retry policy applied to every request.
+    ///
+    /// Requests can override it with the `max_retries` and `retry_backoff_ms` meta
+    /// keys; `max_retries: 0` disables retries for that request. Defaults to no retries.
+    ///
+    /// Once retries run out the download fails with
+    /// `DownloadError::RetriesExhausted` listing every attempt, also when the
+    /// last one received a retryable status.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Sets the `Accept` header sent when a request doesn't already set one.
+    pub fn accept(mut self, preset: AcceptPreset) -> Self {
+        match HeaderValue::from_str(preset.as_str()) {
+            Ok(value) => self.accept = Some(value),
+            Err(_) => warn!("Ignoring invalid Accept header value {:?}", preset.as_str()),
+        }
+        self
+    }
+
+    /// Canonicalizes request URLs before fingerprinting and sending.
+    ///
+    /// Lowercases the host, strips default ports, collapses duplicate slashes and
+    /// removes fragments. Disabled by default.
+    pub fn normalize_urls(mut self, enabled: bool) -> Self {
+        self.normalize_urls = enabled;
+        self
+    }
+
+    /// Also sorts query parameters by key when normalizing URLs.
+    ///
+    /// Only takes effect together with `normalize_urls(true)`. Kept separate since
+    /// some servers treat parameter order as meaningful. Disabled by default.
+    pub fn sort_query_params(mut self, enabled: bool) -> Self {
+        self.sort_query_params = enabled;
+        self
+    }
+
+    /// Opens a fresh connection for every request to these hosts.
+    ///
+    /// Their clients are built with `pool_max_idle_per_host(0)`, working around
+    /// origins that send `Connection: close` or mishandle reuse. A single request
+    /// can opt out of pooling with `disable_pool: true` in its meta. Has no
+    /// effect on a downloader created with `from_client`.
+    pub fn disable_pool_for_hosts(mut self, hosts: &[&str]) -> Self {
+        self.no_pool_hosts
+            .extend(hosts.iter().map(|host| host.to_ascii_lowercase()));
+        self.detach_host_clients();
+        self
+    }
+
+    /// Opens a fresh connection for every request, to every host. Disabled by default.
+    ///
+    /// The base client is rebuilt with `pool_max_idle_per_host(0)` and a new
+    /// unpooled client is built for each request instead of reusing the
+    /// per-host ones, like `disable_pool_for_hosts` applied to all hosts. This
+    /// works around servers that mishandle connection reuse at a steep cost:
+    /// every request pays for a TCP connect and, over HTTPS, a full TLS
+    /// handshake, so expect much lower throughput and higher latency. A
+    /// downloader created with `from_client` keeps the injected client; build
+    /// that client with `pool_max_idle_per_host(0)` instead.
+    pub fn disable_connection_reuse(mut self, disabled: bool) -> Self {
+        self.no_connection_reuse = disabled;
+        self.rebuild_base_client();
+        self
+    }
+
+    /// Refuses TLS versions older than `version`, on every client. Defaults to TLS 1.2.
+    ///
+    /// A server that only supports older versions fails with
+    /// `DownloadError::TlsVersionTooLow`, which is not retried. A
+    /// `tls_for_host` configuration with its own minimum takes precedence for
+    /// its host. TLS 1.0 and 1.1 are only available with the `native-tls`
+    /// backend. A downloader created with `from_client` keeps the injected
+    /// client's setting for requests that aren't proxied.
+    pub fn min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = version;
+        self.rebuild_base_client();
+        self.detach_host_clients();
+        self
+    }
+
+    /// Offers no TLS version newer than `version`, on every client. Not capped by default.
+    ///
+    /// Meant for the rare middlebox that breaks TLS 1.3 handshakes: capping at
+    /// TLS 1.2 avoids it. Like `min_tls_version`, overridden per host by
+    /// `tls_for_host` and not applied to a client injected with `from_client`.
+    pub fn max_tls_version(mut self, version: TlsVersion) -> Self {
+        self.max_tls_version = Some(version);
+        self.rebuild_base_client();
+        self.detach_host_clients();
+        self
+    }
+
+    /// Evicts per-host clients that haven't been used for `ttl`, closing their idle connections.
+    ///
+    /// A crawl over many hosts otherwise keeps a client and its pool alive for
+    /// every host it ever visited. An evicted host gets a fresh client on its
+    /// next request. Sweeps run every `idle_eviction_interval`, as chosen by
+    /// `idle_eviction_mode`. Disabled by default.
+    pub fn host_client_idle_ttl(mut self, ttl: Duration) -> Self {
+        self.host_client_idle_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets how often idle per-host clients are swept. Defaults to 60 seconds.
+    pub fn idle_eviction_interval(mut self, interval: Duration) -> Self {
+        self.idle_eviction_interval = interval;
+        self
+    }
+
+    /// Chooses whether idle clients are swept lazily on lookup or by a background task.
+    ///
+    /// `IdleEvictionMode::Lazy`, the default, spawns nothing but only sweeps
+    /// while requests are made, so clients of a downloader at rest are kept.
+    /// `IdleEvictionMode::Background` starts a task on the first request that
+    /// sweeps every interval until `shutdown` is called or the downloader and
+    /// all its clones are dropped. It needs a Tokio runtime with a timer.
+    pub fn idle_eviction_mode(mut self, mode: IdleEvictionMode) -> Self {
+        self.idle_eviction_mode = mode;
+        self
+    }
+
+    /// Stops the background work of this downloader and its clones.
+    ///
+    /// The idle-client sweeper exits without waiting for its next interval, so
+    /// it no longer keeps the runtime busy; idle clients are then swept lazily
+    /// instead. Downloads in flight are unaffected, see `abort_all` to cancel
+    /// them.
+    pub fn shutdown(&self) {
+        self.host_clients.shutdown();
+    }
+
+    /// Sweeps idle per-host clients if a sweep is due, or starts the background sweeper.
+    async fn evict_idle_host_clients(&self) {
+        let Some(ttl) = self.host_client_idle_ttl else {
+            return;
+        };
+        if self.idle_eviction_mode == IdleEvictionMode::Background
+            && !self.host_clients.is_shut_down()
+        {
+            if self.host_clients.claim_sweeper() {
+                self.spawn_idle_sweeper(ttl);
+            }
+            return;
+        }
+        let now = self.clock.now();
+        if self
+            .host_clients
+            .lazy_sweep_due(now, self.idle_eviction_interval)
         {
-            let clients = self.host_clients.read().await;
-            if let Some(client) = clients.get(host) {
-                return client.clone();
+            let remaining = self.host_clients.evict_idle(ttl, now).await;
+            if let Some(metrics) = &self.metrics {
+                metrics.gauge(METRIC_HOST_CLIENTS, remaining as f64);
             }
         }
+    }
 
-        // Create a new client for this host with optimized settings
-        let host_specific_client = Client::builder()
-            .timeout(self.timeout)
-            .pool_max_idle_per_host(50) // Smaller pool per host to distribute connections
-            .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_keepalive(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(5))
-            .build()
-            .unwrap();
+    /// Starts the task sweeping idle clients every interval until shutdown.
+    fn spawn_idle_sweeper(&self, ttl: Duration) {
+        // A weak reference, so the task never keeps the clients alive.
+        let host_clients = Arc::downgrade(&self.host_clients);
+        let mut shutdown = self.host_clients.subscribe_shutdown();
+        let clock = self.clock.clone();
+        let metrics = self.metrics.clone();
+        let interval = self.idle_eviction_interval;
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = clock.sleep(interval) => {}
+                    // Also resolves with an error once the clients are dropped.
+                    _ = shutdown.changed() => break,
+                }
+                let Some(host_clients) = host_clients.upgrade() else {
+                    break;
+                };
+                let remaining = host_clients.evict_idle(ttl, clock.now()).await;
+                if let Some(metrics) = &metrics {
+                    metrics.gauge(METRIC_HOST_CLIENTS, remaining as f64);
+                }
+            }
+            debug!("Idle host client sweeper stopped");
+        });
+    }
 
-        {
-            let mut clients = self.host_clients.write().await;
-            // Double-check pattern to avoid race condition
-            if let Some(client) = clients.get(host) {
-                return client.clone();
+    /// Rebuilds the base client after a setting it depends on changed; injected clients are kept.
+    fn rebuild_base_client(&mut self) {
+        if !self.per_host_clients {
+            return;
+        }
+        let pool_max_idle = if self.no_connection_reuse { 0 } else { 200 };
+        self.client = build_base_client(
+            self.timeout,
+            pool_max_idle,
+            self.min_tls_version,
+            self.max_tls_version,
+        );
+    }
+
+    /// Starts over with no per-host or proxied clients after a setting they are built with changed.
+    ///
+    /// Clients built before the change, possibly shared with clones, no longer
+    /// match this downloader's settings; a fresh set also keeps the clones
+    /// from picking up clients built with the new ones.
+    fn detach_host_clients(&mut self) {
+        self.host_clients = Arc::new(HostClients::new());
+    }
+
+    /// Reports the progress of streamed uploads sent with `upload`.
+    pub fn on_upload_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(UploadProgress) + Send + Sync + 'static,
+    {
+        self.upload_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Downloads `request` like `download`, keeping downloader errors structured.
+    ///
+    /// `download` returns `SpiderError`, which carries a `DownloadError` only as
+    /// its message. This returns it as `DownloadFailure::Download` instead, so
+    /// callers can match on `DownloadError::RetriesExhausted` and its attempts,
+    /// `Cancelled`, `DeadlineExceeded` and the other variants.
+    pub async fn try_download(&self, request: Request) -> Result<Response, DownloadFailure> {
+        // Subscribed before starting, so only later `abort_all` calls cancel this download.
+        let mut aborted = self.abort.subscribe();
+        let remaining = match self.global_deadline {
+            Some(deadline) => {
+                let now = self.clock.now();
+                if now >= deadline {
+                    return Err(DownloadError::DeadlineExceeded.into());
+                }
+                Some(deadline - now)
+            }
+            None => None,
+        };
+        let deadline = async {
+            match remaining {
+                Some(remaining) => self.clock.sleep(remaining).await,
+                None => std::future::pending().await,
             }
-            clients.insert(host.to_string(), host_specific_client.clone());
+        };
+        tokio::select! {
+            result = self.download_uncancelled(request) => result,
+            _ = aborted.changed() => Err(DownloadError::Cancelled.into()),
+            _ = deadline => Err(DownloadError::DeadlineExceeded.into()),
         }
+    }
+
+    /// Downloads `url` with a plain `GET` request.
+    ///
+    /// Shorthand for building a `Request` and passing it to `download`, so all
+    /// of the downloader's settings apply.
+    pub async fn get(&self, url: &str) -> Result<Response, SpiderError> {
+        self.download(Request::new(parse_url(url)?)).await
+    }
+
+    /// Sends `body` to `url` with a `POST` request through `download`.
+    pub async fn post(&self, url: &str, body: Body) -> Result<Response, SpiderError> {
+        let mut request = Request::new(parse_url(url)?);
+        request.method = Method::POST;
+        request.body = Some(body);
+        self.download(request).await
+    }
+
+    /// Checks that `url`'s host accepts connections, without sending an HTTP request.
+    ///
+    /// Opens a TCP connection and, for `https`, completes a TLS handshake, then
+    /// closes it, reporting the connect and handshake times and the negotiated
+    /// TLS version and ALPN protocol. Addresses pinned with `pin_host` are used
+    /// as is; otherwise the host is resolved and `address_family` applies. Each
+    /// step is bounded by the request timeout. A failure is reported as
+    /// `DownloadError::ProbeFailed`, naming the step that failed. Proxies are
+    /// not used.
+    #[cfg(feature = "rustls-tls")]
+    pub async fn probe(&self, url: &str) -> Result<ProbeResult, SpiderError> {
+        let url = parse_url(url)?;
+        check_url(&url)?;
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addrs = match self.pinned_hosts.get(&host) {
+            Some(pinned) => pinned
+                .iter()
+                .map(|addr| match addr.port() {
+                    0 => SocketAddr::new(addr.ip(), port),
+                    _ => *addr,
+                })
+                .collect(),
+            None => {
+                let lookup_host = host.trim_start_matches('[').trim_end_matches(']');
+                let resolved = tokio::net::lookup_host((lookup_host, port))
+                    .await
+                    .map_err(|e| DownloadError::ProbeFailed {
+                        url: url.to_string(),
+                        stage: "resolve".to_string(),
+                        reason: e.to_string(),
+                    })?;
+                self.address_family.apply(resolved)
+            }
+        };
+        probe::probe(&url, &addrs, self.timeout).await
+    }
+
+    /// Sends `request` with a streamed body of known length.
+    ///
+    /// `Content-Length` is set from `body.len` and progress is reported to the
+    /// `on_upload_progress` callback as chunks are sent. Any body already set on
+    /// the request is replaced. A stream can't be replayed, so uploads are never
+    /// retried.
+    pub async fn upload(
+        &self,
+        request: Request,
+        body: UploadBody,
+    ) -> Result<Response, SpiderError> {
+        let (mut request, client, fingerprint, _proxy) = self.prepare(request).await?;
+        self.authorize(&mut request).await?;
+        let _connection = self.connection_permit()?;
 
-        host_specific_client
+        let UploadBody { stream, len } = body;
+        let progress = self.upload_progress.clone();
+        let mut sent = 0;
+        let counted = stream.map(move |chunk| {
+            if let (Ok(bytes), Some(callback)) = (&chunk, &progress) {
+                sent += bytes.len() as u64;
+                callback(UploadProgress { sent, total: len });
+            }
+            chunk
+        });
+
+        let mut builder = client
+            .request(request.method.clone(), request.url.clone())
+            .headers(request.headers.clone())
+            .header(CONTENT_LENGTH, len);
+        if self.expect_continue && !request.headers.contains_key(EXPECT) {
+            builder = builder.header(EXPECT, HeaderValue::from_static("100-continue"));
+        }
+        let res = builder
+            .body(reqwest::Body::wrap_stream(counted))
+            .send()
+            .await?;
+        let fetched = self
+            .with_body_timeout(&request.url, self.read_response(&request.method, res))
+            .await?;
+        Ok(self.into_response(request, fetched, fingerprint))
     }
+
+    /// Refuses requests while `limit` requests already hold a connection, e.g. to stay clear of `EMFILE`.
+    ///
+    /// A connection is counted from sending until the body has been read, or
+    /// until a streamed body is dropped. Once the limit is reached, further
+    /// requests fail immediately with `DownloadError::ResourceExhausted`
+    /// instead of failing deep in the connector when the process runs out of
+    /// file descriptors; they are not retried. Idle pooled connections are not
+    /// counted, so bound them as well with `disable_pool_for_hosts` or
+    /// `disable_connection_reuse` when descriptors are scarce.
+    pub fn max_open_connections(mut self, limit: usize) -> Self {
+        self.open_connections = Some((limit, Arc::new(Semaphore::new(limit))));
+        self
+    }
+
+    /// Logs requests instead of sending them.
+    ///
+    /// `download` logs the fully prepared request (method, URL, headers and a
+    /// body summary) and returns a synthetic `200 OK` with an empty body and
+    /// `dry_run: true` in its meta. No OAuth2 token is fetched and the signer
+    /// doesn't run, so the logged headers lack those. No proxy or client is
+    /// picked either, leaving the proxy pool and host clients untouched.
+    /// Streaming downloads and uploads still send.
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled;
+        self
+    }
+
+    /// Limits the number of simultaneous downloads from any one host.
+    ///
+    /// Unlike the idle pool size, this bounds active requests: `download` waits
+    /// for a free slot before sending, and holds it across retries. Unlimited by
+    /// default.
+    pub fn max_concurrent_per_host(mut self, limit: usize) -> Self {
+        self.max_concurrent_per_host = Some(limit.max(1));
+        self
+    }
+
+    /// Overrides the concurrency limit for `host`.
+    pub fn max_concurrent_for_host(mut self, host: &str, limit: usize) -> Self {
+        self.host_concurrency
+            .insert(host.to_ascii_lowercase(), limit.max(1));
+        self
+    }
+
+    /// Transforms every response returned by `download` with `mapper`.
+    ///
+    /// Mappers run in the order they were added, after the validators, each
+    /// on the previous one's output; the first error is returned as the
+    /// download's error and skips the remaining mappers. Use them to normalize
+    /// responses inside the downloader, e.g. to strip tracking parameters from
+    /// the final URL or to add computed meta. They don't apply to stubs, dry
+    /// runs or streaming downloads.
+    pub fn map_response<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(Response) -> Result<Response, SpiderError> + Send + Sync + 'static,
+    {
+        self.response_mappers.push(Arc::new(mapper));
+        self
+    }
+
+    /// Runs `validator` on every response returned by `download`.
+    ///
+    /// A rejection is returned as the download's error. Validators run in the
+    /// order they were added, after meta-refresh redirects have been followed.
+    pub fn validator(mut self, validator: Arc<dyn ResponseValidator>) -> Self {
+        self.validators.push((validator, false));
+        self
+    }
+
+    /// Like `validator`, but a rejected response is downloaded again.
+    ///
+    /// Re-downloads are bounded by the retry policy's `max_retries` and spaced
+    /// by its backoff; once they run out, the validator's error is returned.
+    pub fn validator_with_retry(mut self, validator: Arc<dyn ResponseValidator>) -> Self {
+        self.validators.push((validator, true));
+        self
+    }
+
+    /// Restricts or orders the IP address families used for connections.
+    ///
+    /// Applies to the per-host and proxy clients built by the downloader, so it
+    /// has no effect on a downloader created with `from_client`. Defaults to
+    /// `AddressFamily::Any`.
+    pub fn address_family(mut self, family: AddressFamily) -> Self {
+        self.address_family = family;
+        self.detach_host_clients();
+        self
+    }
+
+    /// Retries sends that fail to get a connection, separately from the retry policy.
+    ///
+    /// Under heavy concurrency an attempt can fail before reaching the server
+    /// because no connection could be obtained. Such connect errors are retried
+    /// up to `attempts` more times, `delay` apart, within the same attempt, so
+    /// they neither consume the retry policy's budget nor appear in its history.
+    pub fn pool_acquire_retry(mut self, attempts: u32, delay: Duration) -> Self {
+        self.pool_acquire_retry = Some((attempts, delay));
+        self
+    }
+
+    /// Sends only a fraction of the requests passed to `download`.
+    ///
+    /// Each request is sent with probability `rate`; the others get a synthetic
+    /// `200 OK` with an empty body and `sampled_out: true` in its meta, without
+    /// picking a proxy or client for them. Useful to run a crawl plan at, say,
+    /// 1% of its real traffic. Defaults to 1.0.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Seeds the random source used by `sample_rate`, making sampling reproducible.
+    pub fn sample_seed(mut self, seed: u64) -> Self {
+        self.sampler = Arc::new(Mutex::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Sends `Expect: 100-continue` with requests that carry a body, including uploads.
+    ///
+    /// reqwest sends the body without waiting for the server's `100 Continue`,
+    /// so this does not save bandwidth on rejected uploads; it only satisfies
+    /// servers that require the header. A `417 Expectation Failed` answer is
+    /// retried once without the header, except for uploads whose stream can't
+    /// be replayed. Disabled by default.
+    pub fn expect_continue(mut self, enabled: bool) -> Self {
+        self.expect_continue = enabled;
+        self
+    }
+
+    /// Connects to `addr` for requests to `host`, e.g. to reach one backend behind a load balancer.
+    ///
+    /// DNS is bypassed for `host`, but the request is otherwise unchanged: the
+    /// `Host` header and the TLS server name (SNI) are still taken from the URL,
+    /// and the certificate is verified against it. A port of 0 in `addr` keeps
+    /// the URL's port. Applies to the per-host and proxy clients built by the
+    /// downloader, so it has no effect on a downloader created with `from_client`.
+    pub fn connect_to(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.connect_to.insert(host.to_ascii_lowercase(), addr);
+        self.detach_host_clients();
+        self
+    }
+
+    /// Uses `addrs` for connections to `host` instead of resolving it, e.g. to pin a datacenter.
+    ///
+    /// The system resolver is never consulted for a pinned host; connections
+    /// try the addresses in order. As with `connect_to`, a port of 0 keeps the
+    /// URL's port, and the `Host` header and TLS server name still come from
+    /// the URL. Pins take precedence over `connect_to` for the same host.
+    /// Applies to the per-host and proxy clients built by the downloader, so
+    /// it has no effect on a downloader created with `from_client`.
+    pub fn pin_host(mut self, host: &str, addrs: Vec<SocketAddr>) -> Self {
+        self.pinned_hosts.insert(host.to_ascii_lowercase(), addrs);
+        self.detach_host_clients();
+        self
+    }
+
+    /// Removes every address pinned with `pin_host`, resolving those hosts through DNS again.
+    pub fn clear_pins(mut self) -> Self {
+        self.pinned_hosts.clear();
+        self.detach_host_clients();
+        self
+    }
+
+    /// Stops all downloading at `deadline`, measured on the downloader's clock.
+    ///
+    /// Once it has passed, `download` returns `DownloadError::DeadlineExceeded`
+    /// immediately, and downloads still in flight at that moment are cancelled
+    /// with the same error.
+    pub fn global_deadline(mut self, deadline: Instant) -> Self {
+        self.global_deadline = Some(deadline);
+        self
+    }
+
+    /// Cancels every `download` currently in flight.
+    ///
+    /// Each of them returns `DownloadError::Cancelled` as soon as it is next
+    /// polled, dropping its connection. Downloads started afterwards are not
+    /// affected. Downloads running on clones of this downloader are cancelled too.
+    pub fn abort_all(&self) {
+        self.abort.send_modify(|generation| *generation += 1);
+    }
+
+    /// Serves `response` for every request whose URL matches `url_pattern`, without using the network.
+    ///
+    /// The pattern is matched against the whole prepared URL; `*` matches any
+    /// sequence, so a pattern without `*` is an exact match, e.g.
+    /// `https://api.example.com/flaky*`. Only the status, headers and body of
+    /// `response` are used. Stubs are checked in the order they were added and
+    /// apply to `download` only. Other requests are sent as usual. A stubbed
+    /// request never picks a proxy or client, so it leaves the proxy pool and
+    /// the host clients untouched.
+    pub fn stub(mut self, url_pattern: &str, response: Response) -> Self {
+        self.stubs.push(Stub {
+            pattern: url_pattern.to_string(),
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        });
+        self
+    }
+
+    /// Records each request as sent under `sent_request` in `Response.meta`. Disabled by default.
+    ///
+    /// The record holds the method, URL and headers after default headers,
+    /// cookies, auth and signing have been applied, which helps explain why a
+    /// server rejected a request. Values of `Authorization`, `Proxy-Authorization`,
+    /// `Cookie` and other sensitive headers are replaced with `[redacted]`.
+    /// Headers added by reqwest itself, such as `Host` and `Content-Length`,
+    /// are not included.
+    pub fn record_sent_requests(mut self, enabled: bool) -> Self {
+        self.record_sent_requests = enabled;
+        self
+    }
+
+    /// Reads up to `chunks` body chunks ahead of a `download_stream` consumer. Defaults to 0.
+    ///
+    /// With the default of 0 the connection is read only when the body stream
+    /// is polled. A positive value moves reading to a background task feeding a
+    /// channel of that capacity, so the next chunks are already there when the
+    /// consumer asks; once the channel is full, reading pauses until the
+    /// consumer catches up. At most `chunks` chunks are buffered per stream, in
+    /// addition to what the HTTP stack itself buffers. Dropping the stream
+    /// stops the background task.
+    pub fn stream_buffer_chunks(mut self, chunks: usize) -> Self {
+        self.stream_buffer_chunks = chunks;
+        self
+    }
+
+    /// Controls whether TLS handshakes send the server name (SNI). Enabled by default.
+    ///
+    /// Some internal hosts reject or misroute handshakes carrying SNI. Applies
+    /// to the per-host and proxy clients built by the downloader, so it has no
+    /// effect on a downloader created with `from_client`.
+    pub fn tls_sni(mut self, enabled: bool) -> Self {
+        self.tls_sni = enabled;
+        self.detach_host_clients();
+        self
+    }
+
+    /// Accepts certificates issued for another host name. Dangerous; disabled by default.
+    ///
+    /// Unlike accepting all invalid certificates, the chain is still verified
+    /// against the trusted roots: only the host name check is skipped. Meant
+    /// for internal hosts reached by IP or an alias missing from their
+    /// certificate. Applies to the per-host and proxy clients built by the
+    /// downloader, so it has no effect on a downloader created with `from_client`.
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.accept_invalid_hostnames = accept;
+        self.detach_host_clients();
+        self
+    }
+
+    /// Uses `config` for TLS connections to `host`, e.g. to allow a legacy protocol version for one origin.
+    ///
+    /// Settings set in `config` take precedence over the downloader-wide ones,
+    /// such as `tls_sni` and `danger_accept_invalid_hostnames`, for that host
+    /// only; settings it leaves unset keep the downloader-wide values. The host
+    /// is matched exactly. Applies to the per-host and proxy clients built by
+    /// the downloader, so it has no effect on a downloader created with
+    /// `from_client`.
+    pub fn tls_for_host(mut self, host: &str, config: TlsConfig) -> Self {
+        self.host_tls.insert(host.to_ascii_lowercase(), config);
+        self.detach_host_clients();
+        self
+    }
+
+    /// Retries once over plain HTTP when a TLS handshake fails. Insecure; disabled by default.
+    ///
+    /// Meant for crawling the long tail of sites with broken TLS once that risk
+    /// has been accepted: the retried request and its response can be read and
+    /// altered by anyone on the network path. A TLS failure is not retried over
+    /// HTTPS; a single `http://` attempt replaces it, with the default port if
+    /// the URL had none, and without `Authorization`, `Proxy-Authorization` and
+    /// `Cookie` headers so credentials are not sent in clear. Responses obtained
+    /// this way have `downgraded: true` in their meta. Hosts added with
+    /// `https_only_host` are never downgraded.
+    pub fn allow_https_downgrade_on_tls_error(mut self, allow: bool) -> Self {
+        self.https_downgrade = allow;
+        self
+    }
+
+    /// Never downgrades `host` to plain HTTP, even with `allow_https_downgrade_on_tls_error`.
+    pub fn https_only_host(mut self, host: &str) -> Self {
+        self.https_only_hosts.insert(host.to_ascii_lowercase());
+        self
+    }
+
+    /// Sends request URLs exactly as held by `Request.url`, for servers that need a precise spelling.
+    ///
+    /// URL normalization is skipped even if enabled, and parameters added with
+    /// `RequestExt::with_query` are appended without re-encoding the URL's
+    /// existing query. Only the fragment, which never reaches the server, is
+    /// still removed.
+    ///
+    /// The URL is still a parsed `url::Url`: encoding applied when it was
+    /// parsed (spaces, non-ASCII characters, dot segments) cannot be undone by
+    /// this downloader. Also beware that differently spelled URLs for the same
+    /// resource get different fingerprints, defeating deduplication. Disabled
+    /// by default.
+    pub fn verbatim_urls(mut self, enabled: bool) -> Self {
+        self.verbatim_urls = enabled;
+        self
+    }
+
+    /// Flags responses that `detector` considers soft 404s with `soft_404: true` in their meta.
+    ///
+    /// `SoftNotFoundHeuristic::default()` provides a ready-made detector. It
+    /// pairs well with `download_head_bytes`, since only the start of the body
+    /// is inspected.
+    pub fn soft_not_found_detector(mut self, detector: Arc<dyn SoftNotFoundDetector>) -> Self {
+        self.soft_404_detector = Some(detector);
+        self
+    }
+
+    /// Downloads a request, reading its body into `buf` to reuse its allocation.
+    ///
+    /// `buf` is cleared first and the response body is split off it, so the
+    /// body shares `buf`'s memory. Once the returned response's body is
+    /// dropped, the next call reuses that capacity instead of allocating, which
+    /// saves an allocation per response on crawls of many small pages. Keeping
+    /// the body alive only costs a fresh allocation on the next call.
+    ///
+    /// `max_body_bytes` and decompression apply as for `download`; a
+    /// compressed body is decoded as it arrives into a temporary buffer, so
+    /// `max_decompressed_bytes` stops a decompression bomb mid-stream. The
+    /// request is sent once, without retries, cache, stubs or validators.
+    pub async fn download_into(
+        &self,
+        request: Request,
+        buf: &mut BytesMut,
+    ) -> Result<Response, SpiderError> {
+        let (mut request, client, fingerprint, _proxy) = self.prepare(request).await?;
+        check_url(&request.url)?;
+        self.authorize(&mut request).await?;
+        let _connection = self.connection_permit()?;
+        let mut res = self
+            .send_once(
+                &client,
+                &request.method,
+                &request.url,
+                &request.headers,
+                request.body.as_ref(),
+            )
+            .await?;
+        check_upgrade(&res)?;
+        let url = res.url().clone();
+        let status = res.status();
+        let mut headers = res.headers().clone();
+        let remote_addr = res.remote_addr();
+        let version = res.version();
+
+        let decoder = if self.decompress && !is_bodiless(&request.method, status) {
+            DecoderChain::new(&codings_to_decode(&headers), self.max_decompressed_bytes)
+        } else {
+            None
+        };
+
+        buf.clear();
+        let mut encoded_bytes = None;
+        let mut content_encoding = None;
+        match decoder {
+            None => {
+                self.with_body_timeout(&url, self.read_body_into(&mut res, buf))
+                    .await?
+            }
+            Some(decoder) => {
+                let read = self
+                    .with_body_timeout(&url, self.read_decoded_into(&mut res, decoder, buf))
+                    .await?;
+                if read > 0 {
+                    encoded_bytes = Some(read);
+                    content_encoding = joined_header(&headers, CONTENT_ENCODING);
+                    headers.remove(CONTENT_ENCODING);
+                    headers.remove(TRANSFER_ENCODING);
+                    headers.remove(CONTENT_LENGTH);
+                }
+            }
+        }
+        drop(res);
+
+        let fetched = Fetched {
+            url,
+            status,
+            headers,
+            body: buf.split().freeze(),
+            encoded_bytes,
+            content_encoding,
+            remote_addr,
+            version,
+        };
+        Ok(self.into_response(request, fetched, fingerprint))
+    }
+
+    /// Downloads at most the first `n` bytes of the body, then drops the connection.
+    ///
+    /// Useful to sniff a page without paying for all of it. When the body was
+    /// longer, the response carries `truncated: true` in its meta. The request
+    /// is sent once, without retries, and the body is returned as received,
+    /// without decompression. Unless the request sets its own
+    /// `Accept-Encoding`, it asks for `identity` so the prefix is readable.
+    pub async fn download_head_bytes(
+        &self,
+        mut request: Request,
+        n: usize,
+    ) -> Result<Response, SpiderError> {
+        if !request.headers.contains_key(ACCEPT_ENCODING) {
+            // A prefix of a compressed body can't be decoded on its own.
+            request
+                .headers
+                .insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        }
+        let (mut request, client, fingerprint, _proxy) = self.prepare(request).await?;
+        check_url(&request.url)?;
+        self.authorize(&mut request).await?;
+        let _connection = self.connection_permit()?;
+        let mut res = self
+            .send_once(
+                &client,
+                &request.method,
+                &request.url,
+                &request.headers,
+                request.body.as_ref(),
+            )
+            .await?;
+        check_upgrade(&res)?;
+        let url = res.url().clone();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let remote_addr = res.remote_addr();
+        let version = res.version();
+
+        let read = async {
+            let mut body = Vec::with_capacity(n);
+            let mut truncated = false;
+            while let Some(chunk) = res.chunk().await? {
+                let room = n - body.len();
+                if chunk.len() > room {
+                    body.extend_from_slice(&chunk[..room]);
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok::<_, FetchError>((body, truncated))
+        };
+        let (body, truncated) = self.with_body_timeout(&url, read).await?;
+        // Dropping the response here closes the connection instead of draining it.
+        drop(res);
+
+        let fetched = Fetched {
+            url,
+            status,
+            headers,
+            body: Bytes::from(body),
+            encoded_bytes: None,
+            content_encoding: None,
+            remote_addr,
+            version,
+        };
+        let response = self.into_response(request, fetched, fingerprint);
+        if truncated {
+            response
+                .meta
+                .insert(META_TRUNCATED.into(), Value::Bool(true));
+        }
+        Ok(response)
+    }
+
+    /// Applies URL, header and proxy settings to a request before sending.
+    ///
+    /// Returns the adjusted request, the client to send it with, its
+    /// fingerprint and the proxy it goes through.
+    async fn prepare(
+        &self,
+        request: Request,
+    ) -> Result<(Request, Client, String, Option<String>), SpiderError> {
+        let (request, fingerprint) = self.prepare_request(request);
+        let (client, proxy_url) = self.prepare_client(&request).await?;
+        Ok((request, client, fingerprint, proxy_url))
+    }
+
+    /// Applies URL and header settings to a request, returning it with its fingerprint.
+    ///
+    /// Has no effect on the downloader, unlike `prepare_client`, so requests
+    /// answered without being sent stop here.
+    fn prepare_request(&self, mut request: Request) -> (Request, String) {
+        // Credentials must not be sent in the URL, logged, or recorded in meta.
+        take_url_credentials(&mut request);
+
+        if request.url.fragment().is_some() {
+            request.meta.insert(
+                META_ORIGINAL_URL.into(),
+                Value::String(request.url.to_string()),
+            );
+        }
+        self.rewrite_url(&mut request);
+
+        let fingerprint = request.fingerprint();
+        let request_id = self.request_id_header.as_ref().map(|header| {
+            let id = self.request_id_for(&request, header);
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                request.headers.insert(header.clone(), value);
+            }
+            request
+                .meta
+                .insert(META_REQUEST_ID.into(), Value::String(id.clone()));
+            id
+        });
+        match &request_id {
+            Some(id) => info!(
+                "Downloading {} (fingerprint: {}, request id: {})",
+                request.url, fingerprint, id
+            ),
+            None => info!("Downloading {} (fingerprint: {})", request.url, fingerprint),
+        }
+
+        let headers = &mut request.headers;
+        if let Some(accept) = &self.accept
+            && !headers.contains_key(ACCEPT)
+        {
+            headers.insert(ACCEPT, accept.clone());
+        }
+        if self.decompress
+            && !headers.contains_key(ACCEPT_ENCODING)
+            && let Ok(value) = HeaderValue::from_str(&supported_encodings().join(", "))
+        {
+            headers.insert(ACCEPT_ENCODING, value);
+        }
+        if self.expect_continue && request.body.is_some() && !headers.contains_key(EXPECT) {
+            headers.insert(EXPECT, HeaderValue::from_static("100-continue"));
+        }
+
+        // Generated once per logical request so every retry carries the same key.
+        if self.idempotency_keys && !request.method.is_idempotent() {
+            let key = match headers.get(IDEMPOTENCY_KEY) {
+                Some(existing) => String::from_utf8_lossy(existing.as_bytes()).into_owned(),
+                None => {
+                    let key = Uuid::new_v4().to_string();
+                    if let Ok(value) = HeaderValue::from_str(&key) {
+                        headers.insert(IDEMPOTENCY_KEY, value);
+                    }
+                    key
+                }
+            };
+            request
+                .meta
+                .insert(META_IDEMPOTENCY_KEY.into(), Value::String(key));
+        }
+
+        apply_cookies(&mut request);
+        apply_priority(&mut request);
+
+        // Lets a request opt out of headers the downloader adds by default.
+        for name in &removed_headers(&request) {
+            request.headers.remove(name.as_str());
+        }
+
+        (request, fingerprint)
+    }
+
+    /// Picks the client and proxy for a prepared request, recording them in its meta.
+    ///
+    /// Selecting advances the proxy pool and may build and cache a host client.
+    async fn prepare_client(
+        &self,
+        request: &Request,
+    ) -> Result<(Client, Option<String>), SpiderError> {
+        let (client, proxy_url) = self.client_for(request).await?;
+
+        // Recorded so stored responses describe how they were fetched.
+        let user_agent = request
+            .headers
+            .get(USER_AGENT)
+            .map(|v| Value::String(String::from_utf8_lossy(v.as_bytes()).into_owned()));
+        let proxy = proxy_url
+            .as_deref()
+            .map(|proxy_url| Value::String(redact_proxy_url(proxy_url)));
+        request.meta.insert(
+            META_DOWNLOADER_CONFIG.into(),
+            serde_json::json!({
+                "user_agent": user_agent,
+                "proxy": proxy,
+            }),
+        );
+        Ok((client, proxy_url))
+    }
+
+    /// Returns the client to send `request` with, and the proxy it goes through.
+    async fn client_for(&self, request: &Request) -> Result<(Client, Option<String>), SpiderError> {
+        // Get host-specific client if available, otherwise use default
+        let host = request.url.host_str().unwrap_or("").to_string();
+        // Convert DashMap to HashMap for the host client creation
+        let meta_hashmap: std::collections::HashMap<String, serde_json::Value> = request
+            .meta
+            .iter()
+            .map(|entry| (entry.key().clone().into_owned(), entry.value().clone()))
+            .collect();
+        let proxy_url = self.select_proxy(request);
+        let client = match &proxy_url {
+            Some(proxy_url) => self.proxied_client(proxy_url, &host).await?,
+            None => self.get_or_create_host_client(&host, &meta_hashmap).await?,
+        };
+        Ok((client, proxy_url))
+    }
+
+    /// Finishes a prepared request that is about to be sent: OAuth2 token, signature and `sent_request` record.
+    ///
+    /// Kept out of `prepare` so stubbed, dry-run and sampled-out requests never
+    /// wait for or spend a token. The signer still runs last, on the final
+    /// headers.
+    async fn authorize(&self, request: &mut Request) -> Result<(), DownloadFailure> {
+        if let Some(oauth2) = &self.oauth2
+            && !request.headers.contains_key(AUTHORIZATION)
+            && !removed_headers(request)
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(AUTHORIZATION.as_str()))
+        {
+            let authorization = oauth2.authorization().await?;
+            request.headers.insert(AUTHORIZATION, authorization);
+        }
+
+        if let Some(signer) = &self.signer {
+            signer.sign(request)?;
+        }
+
+        if self.record_sent_requests {
+            let sent = describe_sent_request(request);
+            request.meta.insert(META_SENT_REQUEST.into(), sent);
+        }
+        Ok(())
+    }
+
+    /// Rewrites the URL of `request` into the form it is sent and fingerprinted in.
+    fn rewrite_url(&self, request: &mut Request) {
+        // The fragment never reaches the server, so it must not split fingerprints.
+        request.url.set_fragment(None);
+
+        apply_query(request, self.verbatim_urls);
+
+        if self.normalize_urls && !self.verbatim_urls {
+            normalize_url(&mut request.url, self.sort_query_params);
+        }
+    }
+
+    /// Returns the correlation ID for a request: from meta, then from the header, else a new one.
+    fn request_id_for(&self, request: &Request, header: &HeaderName) -> String {
+        if let Some(id) = request
+            .meta
+            .get(META_REQUEST_ID)
+            .and_then(|v| v.as_str().map(str::to_string))
+        {
+            return id;
+        }
+        if let Some(existing) = request.headers.get(header) {
+            return String::from_utf8_lossy(existing.as_bytes()).into_owned();
+        }
+        if header.as_str() == "traceparent" {
+            // W3C trace context: version-traceid-parentid-flags.
+            let span = Uuid::new_v4().simple().to_string();
+            format!("00-{}-{}-01", Uuid::new_v4().simple(), &span[..16])
+        } else {
+            Uuid::new_v4().to_string()
+        }
+    }
+
+    /// Waits for a download slot on the host of `url`, if the host is limited.
+    async fn acquire_host_permit(&self, url: &Url) -> Option<OwnedSemaphorePermit> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let limit = self
+            .host_concurrency
+            .get(&host)
+            .copied()
+            .or(self.max_concurrent_per_host)?;
+
+        let existing = self.host_semaphores.read().await.get(&host).cloned();
+        let semaphore = match existing {
+            Some(semaphore) => semaphore,
+            None => self
+                .host_semaphores
+                .write()
+                .await
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone(),
+        };
+        // The semaphore is never closed, so acquiring cannot fail.
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Returns the proxy URL for `request`: `proxy` in meta, then proxy rules, the proxy pool, then environment proxies.
+    fn select_proxy(&self, request: &Request) -> Option<String> {
+        if let Some(proxy_url) = request
+            .meta
+            .get("proxy")
+            .and_then(|v| v.as_str().map(str::to_string))
+        {
+            return Some(proxy_url);
+        }
+        if let Some(proxy) = self.proxy_rules.select(&request.url) {
+            return Some(proxy.url.clone());
+        }
+        if self.pool_applies(&request.url)
+            && let Some(proxy) = self.proxy_pool.as_ref().and_then(|pool| pool.select())
+        {
+            return Some(proxy.url.clone());
+        }
+        self.env_proxy
+            .as_ref()
+            .and_then(|env| env.select(&request.url))
+            .map(str::to_string)
+    }
+
+    /// Returns whether `url` would be sent through the proxy pool, absent a meta proxy or rule.
+    fn pool_applies(&self, url: &Url) -> bool {
+        self.proxy_pool
+            .as_ref()
+            .is_some_and(|pool| !pool.is_empty())
+            && !is_bypassed(&self.proxy_rules.bypass, url)
+    }
+
+    /// Feeds the outcome of a request sent through `proxy_url` back to the proxy pool.
+    ///
+    /// `status` is `None` when the request failed without a response.
+    fn record_proxy_outcome(&self, proxy_url: &str, status: Option<StatusCode>) {
+        let Some(pool) = &self.proxy_pool else {
+            return;
+        };
+        let success = status.is_some_and(|status| !matches!(status.as_u16(), 407 | 502 | 504));
+        pool.record(proxy_url, success);
+    }
+
+    /// Returns the client for `host` that routes every request through `proxy_url`.
+    ///
+    /// Clients are kept with the per-host clients, keyed by host and proxy, so
+    /// requests through the same proxy reuse its connections and are evicted
+    /// like the other clients when idle.
+    async fn proxied_client(&self, proxy_url: &str, host: &str) -> Result<Client, SpiderError> {
+        if self.no_connection_reuse {
+            return self.build_proxied_client(proxy_url, host);
+        }
+        self.evict_idle_host_clients().await;
+        let key = format!("{} via {}", host, proxy_url);
+        let now = self.clock.now();
+        if let Some(client) = self.host_clients.get(&key, now).await {
+            return Ok(client);
+        }
+        let client = self.build_proxied_client(proxy_url, host)?;
+        let (client, count) = self.host_clients.insert(&key, client, now).await;
+        debug!(
+            "Using client for host {} via {} ({} host clients)",
+            host,
+            redact_proxy_url(proxy_url),
+            count
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.gauge(METRIC_HOST_CLIENTS, count as f64);
+        }
+        Ok(client)
+    }
+
+    /// Builds a client for `host` that routes every request through `proxy_url`.
+    fn build_proxied_client(&self, proxy_url: &str, host: &str) -> Result<Client, SpiderError> {
+        let proxy = Proxy::all(proxy_url).map_err(|e| SpiderError::ReqwestError(e.into()))?;
+        let pool_max_idle = if self.no_connection_reuse { 0 } else { 50 };
+        self.client_builder(host)
+            .proxy(proxy)
+            .pool_max_idle_per_host(pool_max_idle)
+            .build()
+            .map_err(|e| SpiderError::ReqwestError(e.into()))
+    }
+
+    /// Sends a prepared request, retrying according to the retry policy.
+    async fn send_with_retries(
+        &self,
+        client: &Client,
+        request: &Request,
+        fingerprint: &str,
+    ) -> Result<Fetched, DownloadFailure> {
+        let url = &request.url;
+        let policy = self
+            .retry_policy
+            .with_overrides(|key| request.meta.get(key).and_then(|v| v.as_u64()));
+        let mut retry = 0;
+        let mut delay = Duration::ZERO;
+        let mut waited = Duration::ZERO;
+        let mut history = Vec::new();
+        loop {
+            let result = self
+                .fetch_once(
+                    client,
+                    &request.method,
+                    url,
+                    &request.headers,
+                    request.body.as_ref(),
+                )
+                .await;
+            if let Err(e) = &result
+                && is_tls_error(e)
+                && self.may_downgrade(url)
+            {
+                return self.fetch_downgraded(client, request, e).await;
+            }
+            let attempt = retry + 1;
+            let mut server_delay = None;
+            let failure = match &result {
+                Ok(fetched) => match policy.action_for(fetched.status) {
+                    RetryAction::Never => None,
+                    action => {
+                        if action == RetryAction::RetryAfter {
+                            server_delay =
+                                fetched.headers.get(RETRY_AFTER).and_then(parse_retry_after);
+                        }
+                        Some(AttemptError {
+                            attempt,
+                            delay,
+                            kind: AttemptErrorKind::Status,
+                            message: format!("status {}", fetched.status),
+                        })
+                    }
+                },
+                Err(e) if is_transient(e) => Some(AttemptError {
+                    attempt,
+                    delay,
+                    kind: attempt_error_kind(e),
+                    message: e.to_string(),
+                }),
+                Err(_) => None,
+            };
+            let Some(failure) = failure else {
+                if retry > 0 {
+                    info!(
+                        "Download of {} (fingerprint: {}) finished on attempt {}",
+                        url, fingerprint, attempt
+                    );
+                }
+                return Ok(result?);
+            };
+            let next_delay = match server_delay {
+                Some(server_delay) => policy
+                    .max_backoff
+                    .map_or(server_delay, |max| server_delay.min(max)),
+                None => policy.next_delay(retry, delay),
+            };
+            if retry >= policy.max_retries || !policy.within_budget(waited, next_delay) {
+                if policy.max_retries == 0 {
+                    return Ok(result?);
+                }
+                warn!(
+                    "Giving up on {} (fingerprint: {}) after {} attempts: {}",
+                    url, fingerprint, attempt, failure.message
+                );
+                history.push(failure);
+                return Err(DownloadError::RetriesExhausted { attempts: history }.into());
+            }
+            delay = next_delay;
+            warn!(
+                "Attempt {} for {} (fingerprint: {}) failed: {}; retrying in {:?}",
+                attempt, url, fingerprint, failure.message, delay
+            );
+            history.push(failure);
+            self.clock.sleep(delay).await;
+            waited += delay;
+            retry += 1;
+        }
+    }
+
+    /// Returns whether a TLS failure on `url` may be retried over plain HTTP.
+    fn may_downgrade(&self, url: &Url) -> bool {
+        self.https_downgrade
+            && url.scheme() == "https"
+            && !url
+                .host_str()
+                .is_some_and(|host| self.https_only_hosts.contains(&host.to_ascii_lowercase()))
+    }
+
+    /// Sends `request` once over plain HTTP after its HTTPS attempt failed with `tls_error`.
+    async fn fetch_downgraded(
+        &self,
+        client: &Client,
+        request: &Request,
+        tls_error: &FetchError,
+    ) -> Result<Fetched, DownloadFailure> {
+        let mut url = request.url.clone();
+        if url.port() == Some(443) {
+            let _ = url.set_port(None);
+        }
+        // Only fails for URLs that can't have an HTTP scheme, which an `https` URL always can.
+        let _ = url.set_scheme("http");
+        warn!(
+            "TLS handshake with {} failed ({}); retrying over plain HTTP as {}",
+            request.url, tls_error, url
+        );
+        let mut headers = request.headers.clone();
+        for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+            headers.remove(name);
+        }
+        let fetched = self
+            .fetch_once(
+                client,
+                &request.method,
+                &url,
+                &headers,
+                request.body.as_ref(),
+            )
+            .await?;
+        request
+            .meta
+            .insert(META_DOWNGRADED.into(), Value::Bool(true));
+        Ok(fetched)
+    }
+
+    /// Follows meta-refresh redirects when enabled, recording them in the request's meta.
+    async fn follow_meta_refreshes(
+        &self,
+        client: &Client,
+        request: &Request,
+        mut fetched: Fetched,
+    ) -> Result<Fetched, DownloadFailure> {
+        if !self.follow_meta_refresh {
+            return Ok(fetched);
+        }
+        let mut chain = Vec::new();
+        let mut cross_origin = false;
+        while chain.len() < self.max_meta_refresh_hops
+            && fetched.status.is_success()
+            && is_html(&fetched.headers)
+        {
+            let Some(target) = find_meta_refresh(&fetched.body, &fetched.url) else {
+                break;
+            };
+            check_url(&target)?;
+            debug!("Following meta refresh from {} to {}", fetched.url, target);
+            // Once the chain has left the request's origin, credentials stay behind.
+            cross_origin |= target.origin() != request.url.origin();
+            fetched = if cross_origin {
+                self.fetch_cross_origin(request, &target).await?
+            } else {
+                self.fetch_once(client, &Method::GET, &target, &request.headers, None)
+                    .await?
+            };
+            chain.push(Value::String(target.to_string()));
+        }
+        if !chain.is_empty() {
+            request
+                .meta
+                .insert(META_REDIRECT_CHAIN.into(), Value::Array(chain));
+        }
+        Ok(fetched)
+    }
+
+    /// Fetches a meta-refresh `target` on another origin than `request`.
+    ///
+    /// As with reqwest's redirect policy, credentials are not carried over:
+    /// `Authorization`, `Proxy-Authorization`, `Cookie` and `Idempotency-Key`
+    /// are dropped and Digest challenges are left unanswered. The signer signs
+    /// the hop afresh, and it is sent with the client and proxy for the
+    /// target's host.
+    async fn fetch_cross_origin(
+        &self,
+        request: &Request,
+        target: &Url,
+    ) -> Result<Fetched, DownloadFailure> {
+        let mut hop = request.clone();
+        hop.url = target.clone();
+        hop.method = Method::GET;
+        hop.body = None;
+        for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+            hop.headers.remove(name);
+        }
+        hop.headers.remove(IDEMPOTENCY_KEY);
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut hop)?;
+        }
+        let (client, proxy_url) = self.client_for(&hop).await?;
+        let result = self
+            .fetch_once_with(&client, &Method::GET, target, &hop.headers, None, None)
+            .await;
+        if let Some(proxy_url) = &proxy_url {
+            self.record_proxy_outcome(
+                proxy_url,
+                result.as_ref().ok().map(|fetched| fetched.status),
+            );
+        }
+        Ok(result?)
+    }
+
+    /// Runs the validators on a fetched response.
+    ///
+    /// On rejection, returns the validator's error and whether it asked for a retry.
+    fn validate(&self, request: &Request, fetched: &Fetched) -> Result<(), (SpiderError, bool)> {
+        if self.validators.is_empty() {
+            return Ok(());
+        }
+        let response = Response {
+            url: fetched.url.clone(),
+            status: fetched.status,
+            headers: fetched.headers.clone(),
+            body: fetched.body.clone(),
+            request_url: request.url.clone(),
+            meta: request.meta.clone(),
+            cached: false,
+        };
+        for (validator, retry) in &self.validators {
+            validator.validate(&response).map_err(|err| (err, *retry))?;
+        }
+        Ok(())
+    }
+
+    /// Downloads a request; `download` wraps this to make it cancellable.
+    async fn download_uncancelled(&self, request: Request) -> Result<Response, DownloadFailure> {
+        let (mut request, fingerprint) = self.prepare_request(request);
+        check_url(&request.url)?;
+        // Requests answered without being sent never pick a proxy or client.
+        if let Some(stub) = self
+            .stubs
+            .iter()
+            .find(|stub| glob_match(&stub.pattern, request.url.as_str()))
+        {
+            debug!("Serving {} from stub {}", request.url, stub.pattern);
+            let Request { url, meta, .. } = request;
+            meta.insert(META_FINGERPRINT.into(), Value::String(fingerprint));
+            return Ok(Response {
+                url: url.clone(),
+                status: stub.status,
+                headers: stub.headers.clone(),
+                body: stub.body.clone(),
+                request_url: url,
+                meta,
+                cached: false,
+            });
+        }
+        if self.dry_run {
+            return Ok(self.dry_run_response(request, fingerprint));
+        }
+        if !self.sampled_in() {
+            debug!(
+                "Skipping {} (fingerprint: {}): sampled out",
+                request.url, fingerprint
+            );
+            return Ok(empty_response(request, fingerprint, META_SAMPLED_OUT));
+        }
+        let (client, proxy) = self.prepare_client(&request).await?;
+        let _permit = self.acquire_host_permit(&request.url).await;
+        // After waiting for the permit, so the token is as fresh as possible.
+        self.authorize(&mut request).await?;
+        let host = request.url.host_str().unwrap_or("").to_string();
+        let tag = request
+            .meta
+            .get(META_TAG)
+            .and_then(|v| v.as_str().map(str::to_string));
+        let bearer = self
+            .oauth2
+            .as_ref()
+            .and_then(|_| request.headers.get(AUTHORIZATION).cloned());
+        let result = self.fetch_validated(&client, request, fingerprint).await;
+        if let (Some(oauth2), Some(bearer)) = (&self.oauth2, &bearer)
+            && result
+                .as_ref()
+                .is_ok_and(|response| response.status == StatusCode::UNAUTHORIZED)
+        {
+            oauth2.invalidate(bearer);
+        }
+        let success = result
+            .as_ref()
+            .is_ok_and(|response| response.status.as_u16() < 400);
+        self.stats.record(&host, success);
+        self.stats.record_tag(
+            tag.as_deref().unwrap_or(DEFAULT_STATS_TAG),
+            success,
+            result.as_ref().ok().map(|response| response.body.len()),
+        );
+        if let Some(proxy) = &proxy {
+            self.record_proxy_outcome(proxy, result.as_ref().ok().map(|response| response.status));
+        }
+        result
+    }
+
+    /// Sends a prepared request, follows meta refreshes and runs the validators.
+    async fn fetch_validated(
+        &self,
+        client: &Client,
+        request: Request,
+        fingerprint: String,
+    ) -> Result<Response, DownloadFailure> {
+        let policy = self
+            .retry_policy
+            .with_overrides(|key| request.meta.get(key).and_then(|v| v.as_u64()));
+        let mut rejections = 0;
+        let mut delay = Duration::ZERO;
+        let fetched = loop {
+            let fetched = self
+                .send_with_retries(client, &request, &fingerprint)
+                .await?;
+            let fetched = self
+                .follow_meta_refreshes(client, &request, fetched)
+                .await?;
+            match self.validate(&request, &fetched) {
+                Ok(()) => break fetched,
+                Err((err, true)) if rejections < policy.max_retries => {
+                    delay = policy.next_delay(rejections, delay);
+                    warn!(
+                        "Response from {} (fingerprint: {}) rejected: {}; retrying in {:?}",
+                        fetched.url, fingerprint, err, delay
+                    );
+                    self.clock.sleep(delay).await;
+                    rejections += 1;
+                }
+                Err((err, _)) => return Err(err.into()),
+            }
+        };
+        let response = self.into_response(request, fetched, fingerprint);
+        Ok(self
+            .response_mappers
+            .iter()
+            .try_fold(response, |response, mapper| mapper(response))?)
+    }
+
+    /// Logs a prepared request and answers it with an empty `200 OK`.
+    fn dry_run_response(&self, request: Request, fingerprint: String) -> Response {
+        info!(
+            "Dry run: {} {} (fingerprint: {}), headers: {:?}, body: {}",
+            request.method,
+            request.url,
+            fingerprint,
+            request.headers,
+            describe_body(request.body.as_ref())
+        );
+        empty_response(request, fingerprint, META_DRY_RUN)
+    }
+
+    /// Decides whether the next request is sent, according to the sample rate.
+    fn sampled_in(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let mut sampler = self.sampler.lock().unwrap_or_else(|e| e.into_inner());
+        sampler.random::<f64>() < self.sample_rate
+    }
+
+    /// Turns the final attempt into a `Response`, recording download details in meta.
+    fn into_response(&self, request: Request, fetched: Fetched, fingerprint: String) -> Response {
+        debug!(
+            "Received {} body bytes from {}",
+            fetched.body.len(),
+            fetched.url
+        );
+        self.body_sizes.record(fetched.body.len());
+        if let Some(metrics) = &self.metrics {
+            metrics.histogram(METRIC_BODY_BYTES, fetched.body.len() as f64);
+        }
+
+        let Request { url, meta, .. } = request;
+        meta.insert(META_FINGERPRINT.into(), Value::String(fingerprint));
+        let fetched_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        meta.insert(META_FETCHED_AT.into(), Value::from(fetched_at));
+        let config = meta.get(META_DOWNLOADER_CONFIG).map(|v| v.clone());
+        if let Some(Value::Object(mut config)) = config {
+            config.insert(
+                "protocol".to_string(),
+                Value::String(format!("{:?}", fetched.version)),
+            );
+            meta.insert(META_DOWNLOADER_CONFIG.into(), Value::Object(config));
+        }
+        if let Some(detector) = &self.soft_404_detector {
+            let prefix = &fetched.body[..fetched.body.len().min(SOFT_404_PREFIX_LEN)];
+            if detector.is_soft_404(fetched.status, &fetched.headers, prefix) {
+                debug!("{} looks like a soft 404", fetched.url);
+                meta.insert(META_SOFT_404.into(), Value::Bool(true));
+            }
+        }
+        if is_malformed_redirect(fetched.status, &fetched.headers) {
+            warn!(
+                "{} answered {} without a Location header",
+                fetched.url, fetched.status
+            );
+            meta.insert(META_MALFORMED_REDIRECT.into(), Value::Bool(true));
+        }
+        if let Some(rate_limit) = RateLimitInfo::from_headers(&fetched.headers) {
+            meta.insert(META_RATE_LIMIT.into(), rate_limit.to_value());
+        }
+        if let Some(remote_addr) = fetched.remote_addr {
+            meta.insert(
+                META_REMOTE_ADDR.into(),
+                Value::String(remote_addr.to_string()),
+            );
+        }
+        if let Some(encoded_bytes) = fetched.encoded_bytes {
+            meta.insert(META_ENCODED_BYTES.into(), Value::from(encoded_bytes));
+            meta.insert(META_DECODED_BYTES.into(), Value::from(fetched.body.len()));
+        }
+        if let Some(content_encoding) = fetched.content_encoding {
+            meta.insert(
+                META_ORIGINAL_CONTENT_ENCODING.into(),
+                Value::String(content_encoding),
+            );
+        }
+
+        Response {
+            url: fetched.url,
+            status: fetched.status,
+            headers: fetched.headers,
+            body: fetched.body,
+            request_url: url,
+            meta,
+            // Always a network response; caching layers use `ResponseExt::mark_cached`.
+            cached: false,
+        }
+    }
+
+    /// Sends a single attempt of a request and reads its body.
+    async fn fetch_once(
+        &self,
+        client: &Client,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: Option<&Body>,
+    ) -> Result<Fetched, FetchError> {
+        self.fetch_once_with(
+            client,
+            method,
+            url,
+            headers,
+            body,
+            self.digest_auth.as_deref(),
+        )
+        .await
+    }
+
+    /// Like `fetch_once`, answering Digest challenges with `digest_auth` if given.
+    async fn fetch_once_with(
+        &self,
+        client: &Client,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: Option<&Body>,
+        digest_auth: Option<&DigestAuth>,
+    ) -> Result<Fetched, FetchError> {
+        let _connection = self.connection_permit().map_err(FetchError::Download)?;
+        // Digest credentials only apply to requests without their own `Authorization`.
+        let digest = digest_auth.filter(|_| !headers.contains_key(AUTHORIZATION));
+        let mut headers = Cow::Borrowed(headers);
+        if let Some(authorization) = digest.and_then(|digest| digest.authorization(method, url)) {
+            headers.to_mut().insert(AUTHORIZATION, authorization);
+        }
+        let mut res = self
+            .send_once(client, method, url, &headers, body)
+            .await
+            .map_err(|e| self.classify_send_error(e, url))?;
+        if res.status() == StatusCode::EXPECTATION_FAILED && headers.contains_key(EXPECT) {
+            debug!(
+                "{} rejected Expect: 100-continue; resending without it",
+                url
+            );
+            headers.to_mut().remove(EXPECT);
+            res = self
+                .send_once(client, method, url, &headers, body)
+                .await
+                .map_err(|e| self.classify_send_error(e, url))?;
+        }
+        if res.status() == StatusCode::UNAUTHORIZED
+            && let Some(authorization) =
+                digest.and_then(|digest| digest.respond(res.headers(), method, url))
+        {
+            debug!("Answering the Digest challenge of {}", url);
+            headers.to_mut().insert(AUTHORIZATION, authorization);
+            res = self
+                .send_once(client, method, url, &headers, body)
+                .await
+                .map_err(|e| self.classify_send_error(e, url))?;
+        }
+        self.with_body_timeout(url, self.read_response(method, res))
+            .await
+    }
+
+    /// Runs a body read, failing with `BodyReadTimeout` once `body_read_timeout` has passed.
+    async fn with_body_timeout<T>(
+        &self,
+        url: &Url,
+        read: impl Future<Output = Result<T, FetchError>>,
+    ) -> Result<T, FetchError> {
+        let Some(timeout) = self.body_read_timeout else {
+            return read.await;
+        };
+        tokio::select! {
+            result = read => result,
+            _ = self.clock.sleep(timeout) => {
+                debug!("Body of {} stalled for {:?}", url, timeout);
+                Err(FetchError::Download(DownloadError::BodyReadTimeout {
+                    url: url.to_string(),
+                    timeout,
+                }))
+            }
+        }
+    }
+
+    /// Takes one of the `max_open_connections` slots, held until the permit is dropped.
+    fn connection_permit(&self) -> Result<Option<OwnedSemaphorePermit>, DownloadError> {
+        let Some((limit, semaphore)) = &self.open_connections else {
+            return Ok(None);
+        };
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Ok(Some(permit)),
+            Err(_) => Err(DownloadError::ResourceExhausted { limit: *limit }),
+        }
+    }
+
+    /// Reports a handshake that failed on the protocol version as `TlsVersionTooLow`.
+    fn classify_send_error(&self, err: reqwest::Error, url: &Url) -> FetchError {
+        if !(err.is_connect()
+            && error_chain_contains(
+                &err,
+                &[
+                    "protocolversion",
+                    "protocol version",
+                    "unsupported protocol",
+                    "wrong version number",
+                ],
+            ))
+        {
+            return FetchError::Transport(err);
+        }
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let min_version = self
+            .host_tls
+            .get(&host)
+            .and_then(|tls| tls.min_version)
+            .unwrap_or(self.min_tls_version);
+        FetchError::Download(DownloadError::TlsVersionTooLow {
+            url: url.to_string(),
+            min_version: min_version.to_string(),
+        })
+    }
+
+    /// Sends a request, waiting and trying again when no connection could be obtained.
+    async fn send_once(
+        &self,
+        client: &Client,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: Option<&Body>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let (attempts, delay) = self.pool_acquire_retry.unwrap_or((0, Duration::ZERO));
+        let mut tries = 0;
+        loop {
+            match build_request(client, method, url, headers, body)
+                .send()
+                .await
+            {
+                Err(e) if e.is_connect() && tries < attempts => {
+                    debug!(
+                        "No connection for {} ({}); waiting {:?} before trying again",
+                        url, e, delay
+                    );
+                    self.clock.sleep(delay).await;
+                    tries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Reads the body of a response, decompressing it when enabled.
+    async fn read_response(
+        &self,
+        method: &Method,
+        mut res: reqwest::Response,
+    ) -> Result<Fetched, FetchError> {
+        check_upgrade(&res).map_err(FetchError::Download)?;
+        let url = res.url().clone();
+        let status = res.status();
+        let mut headers = res.headers().clone();
+        let remote_addr = res.remote_addr();
+        let version = res.version();
+
+        let decoder = if self.decompress && !is_bodiless(method, status) {
+            // `None` unless every coding is understood; otherwise the raw body is kept.
+            DecoderChain::new(&codings_to_decode(&headers), self.max_decompressed_bytes)
+        } else {
+            None
+        };
+
+        let (body, encoded_bytes, content_encoding) = match decoder {
+            None if self.max_body_bytes.is_none() => (res.bytes().await?, None, None),
+            None => {
+                let mut body = BytesMut::new();
+                self.read_body_into(&mut res, &mut body).await?;
+                (body.freeze(), None, None)
+            }
+            Some(decoder) => {
+                let mut body = BytesMut::new();
+                let encoded_bytes = self.read_decoded_into(&mut res, decoder, &mut body).await?;
+                if encoded_bytes == 0 {
+                    // Nothing was encoded, so the headers still describe the (empty) body.
+                    (Bytes::new(), None, None)
+                } else {
+                    let content_encoding = joined_header(&headers, CONTENT_ENCODING);
+                    // The body no longer matches these headers once decoded.
+                    headers.remove(CONTENT_ENCODING);
+                    headers.remove(TRANSFER_ENCODING);
+                    headers.remove(CONTENT_LENGTH);
+                    (body.freeze(), Some(encoded_bytes), content_encoding)
+                }
+            }
+        };
+
+        Ok(Fetched {
+            url,
+            status,
+            headers,
+            body,
+            encoded_bytes,
+            content_encoding,
+            remote_addr,
+            version,
+        })
+    }
+
+    /// Appends the body of `res` to `buf`, enforcing `max_body_bytes`.
+    async fn read_body_into(
+        &self,
+        res: &mut reqwest::Response,
+        buf: &mut BytesMut,
+    ) -> Result<(), FetchError> {
+        let too_large = |limit| FetchError::Download(DownloadError::BodyTooLarge { limit });
+        if let Some(len) = res.content_length() {
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            if let Some(limit) = self.max_body_bytes
+                && len > limit
+            {
+                return Err(too_large(limit));
+            }
+            buf.reserve(len);
+        }
+        let start = buf.len();
+        while let Some(chunk) = res.chunk().await? {
+            if let Some(limit) = self.max_body_bytes
+                && buf.len() - start + chunk.len() > limit
+            {
+                return Err(too_large(limit));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(())
+    }
+
+    /// Decodes the body of `res` through `decoder` as it arrives and appends the result to `buf`.
+    ///
+    /// Returns the number of encoded bytes read. An empty body is left empty
+    /// rather than handed to the decoders, which reject empty input.
+    async fn read_decoded_into(
+        &self,
+        res: &mut reqwest::Response,
+        mut decoder: DecoderChain,
+        buf: &mut BytesMut,
+    ) -> Result<usize, FetchError> {
+        let mut encoded_bytes = 0;
+        while let Some(chunk) = res.chunk().await? {
+            encoded_bytes += chunk.len();
+            if let Some(limit) = self.max_body_bytes
+                && encoded_bytes > limit
+            {
+                return Err(FetchError::Download(DownloadError::BodyTooLarge { limit }));
+            }
+            decoder.write(&chunk).map_err(FetchError::Download)?;
+        }
+        if encoded_bytes > 0 {
+            buf.extend_from_slice(&decoder.finish().map_err(FetchError::Download)?);
+        }
+        Ok(encoded_bytes)
+    }
+
+    /// Builds a client for `host` with the per-host settings and the given idle pool size.
+    fn build_host_client(&self, host: &str, pool_max_idle: usize) -> Result<Client, SpiderError> {
+        self.client_builder(host)
+            .pool_max_idle_per_host(pool_max_idle)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| SpiderError::ReqwestError(e.into()))
+    }
+
+    /// Starts a client builder for `host` with the settings shared by every client the downloader builds.
+    fn client_builder(&self, host: &str) -> ClientBuilder {
+        let mut builder = with_tls_backend(Client::builder())
+            .no_proxy()
+            .timeout(self.timeout)
+            .tls_sni(self.tls_sni)
+            .danger_accept_invalid_hostnames(self.accept_invalid_hostnames)
+            .min_tls_version(self.min_tls_version.to_reqwest());
+        if let Some(version) = self.max_tls_version {
+            builder = builder.max_tls_version(version.to_reqwest());
+        }
+        if let Some(tls) = self.host_tls.get(&host.to_ascii_lowercase()) {
+            builder = tls.apply(builder);
+        }
+        for (host, addr) in &self.connect_to {
+            builder = builder.resolve(host, *addr);
+        }
+        for (host, addrs) in &self.pinned_hosts {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        if self.address_family == AddressFamily::Any {
+            return builder;
+        }
+        builder.dns_resolver(Arc::new(FamilyResolver {
+            family: self.address_family,
+        }))
+    }
+
+    /// Gets or creates a host-specific client with optimized settings for that host
+    async fn get_or_create_host_client(
+        &self,
+        host: &str,
+        meta: &std::collections::HashMap<String, serde_json::Value>,
+    ) -> Result<Client, SpiderError> {
+        if !self.per_host_clients {
+            return Ok(self.client.clone());
+        }
+
+        let pool_disabled_for_host = self.no_pool_hosts.contains(&host.to_ascii_lowercase());
+        let pool_disabled_by_meta = meta
+            .get(META_DISABLE_POOL)
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if self.no_connection_reuse || (pool_disabled_by_meta && !pool_disabled_for_host) {
+            // A one-off request without pooling must not replace the host's shared client.
+            return self.build_host_client(host, 0);
+        }
+
+        self.evict_idle_host_clients().await;
+        let now = self.clock.now();
+        if let Some(client) = self.host_clients.get(host, now).await {
+            return Ok(client);
+        }
+
+        // Smaller pool per host to distribute connections
+        let pool_max_idle = if pool_disabled_for_host { 0 } else { 50 };
+        let host_specific_client = self.build_host_client(host, pool_max_idle)?;
+
+        // Another task may have stored a client for the host in the meantime.
+        let (client, count) = self
+            .host_clients
+            .insert(host, host_specific_client, now)
+            .await;
+        debug!("Using client for host {} ({} host clients)", host, count);
+        if let Some(metrics) = &self.metrics {
+            metrics.gauge(METRIC_HOST_CLIENTS, count as f64);
+        }
+
+        Ok(client)
+    }
+}
+
+/// Builds the shared base client with the given idle pool size per host and TLS versions.
+fn build_base_client(
+    timeout: Duration,
+    pool_max_idle: usize,
+    min_tls_version: TlsVersion,
+    max_tls_version: Option<TlsVersion>,
+) -> Client {
+    let mut builder =
+        with_tls_backend(Client::builder()).min_tls_version(min_tls_version.to_reqwest());
+    if let Some(version) = max_tls_version {
+        builder = builder.max_tls_version(version.to_reqwest());
+    }
+    builder
+        .no_proxy()
+        .timeout(timeout)
+        .pool_max_idle_per_host(pool_max_idle)
+        .pool_idle_timeout(Duration::from_secs(120))
+        .tcp_keepalive(Duration::from_secs(60))
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap()
+}
+
+/// Selects the TLS backend chosen through the `rustls-tls` / `native-tls` features.
+fn with_tls_backend(builder: ClientBuilder) -> ClientBuilder {
+    #[cfg(feature = "rustls-tls")]
+    let builder = builder.tls_backend_rustls();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    let builder = builder.tls_backend_native();
+    builder
 }
 
 impl Default for ReqwestClientDownloader {
@@ -0,0 +1,137 @@
+//! Convenience methods on `Response`.
+//!
+//! `Response` is defined in `spider-util`, so downloader-level helpers are
+//! provided through the `ResponseExt` extension trait.
+
+use crate::cookie::{Cookie, parse_set_cookies};
+use crate::decompress::BodyDecoder;
+use crate::error::DownloadError;
+use crate::text::{TextDecodePolicy, decode_text};
+use http::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use spider_util::error::SpiderError;
+use spider_util::response::Response;
+use std::borrow::Cow;
+
+/// Maximum number of body bytes quoted in decode errors.
+const SNIPPET_LEN: usize = 200;
+
+/// Extension methods for `Response`.
+pub trait ResponseExt {
+    /// Deserializes a JSON body after checking that `Content-Type` is JSON.
+    ///
+    /// Accepts `application/json`, `text/json` and any `+json` media type. A body
+    /// still carrying a `gzip`/`deflate` `Content-Encoding` is decoded first.
+    /// Any other content type fails with `DownloadError::UnexpectedContentType`,
+    /// quoting the actual type and the start of the body, which tells a bot-block
+    /// or error page apart from malformed JSON.
+    fn json<T: DeserializeOwned>(&self) -> Result<T, SpiderError>;
+
+    /// Deserializes a JSON body regardless of its `Content-Type`.
+    ///
+    /// Useful for servers that mislabel JSON as `text/plain` or `text/html`.
+    fn json_lenient<T: DeserializeOwned>(&self) -> Result<T, SpiderError>;
+
+    /// Decodes the body as UTF-8 text, replacing invalid sequences.
+    fn text(&self) -> Result<String, SpiderError> {
+        self.text_with(TextDecodePolicy::Lossy)
+    }
+
+    /// Decodes the body as UTF-8 text using `policy` for invalid sequences.
+    fn text_with(&self, policy: TextDecodePolicy) -> Result<String, SpiderError>;
+
+    /// Parses the cookies set by the response's `Set-Cookie` headers.
+    ///
+    /// Malformed headers are skipped rather than failing the whole call.
+    fn cookies(&self) -> Vec<Cookie>;
+
+    /// Flags the response as served from a cache rather than the network.
+    ///
+    /// Downloaders always produce responses with `cached: false`; caching
+    /// wrappers call this when they answer from their store.
+    fn mark_cached(&mut self);
+}
+
+impl ResponseExt for Response {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, SpiderError> {
+        let content_type = header_str(self, CONTENT_TYPE).unwrap_or_default();
+        if !is_json_content_type(&content_type) {
+            let body = decoded_body(self).unwrap_or(Cow::Borrowed(&self.body));
+            return Err(DownloadError::UnexpectedContentType {
+                expected: "JSON".to_string(),
+                got: content_type,
+                snippet: snippet(&body),
+            }
+            .into());
+        }
+        self.json_lenient()
+    }
+
+    fn json_lenient<T: DeserializeOwned>(&self) -> Result<T, SpiderError> {
+        let body = decoded_body(self)?;
+        serde_json::from_slice(&body).map_err(|e| {
+            DownloadError::Decode {
+                reason: e.to_string(),
+                snippet: snippet(&body),
+            }
+            .into()
+        })
+    }
+
+    fn text_with(&self, policy: TextDecodePolicy) -> Result<String, SpiderError> {
+        decode_text(&decoded_body(self)?, policy)
+    }
+
+    fn cookies(&self) -> Vec<Cookie> {
+        parse_set_cookies(&self.headers)
+    }
+
+    fn mark_cached(&mut self) {
+        self.cached = true;
+    }
+}
+
+/// Returns the body, decoding it if a supported `Content-Encoding` is still set.
+pub(crate) fn decoded_body(response: &Response) -> Result<Cow<'_, [u8]>, SpiderError> {
+    let Some(encoding) = header_str(response, CONTENT_ENCODING) else {
+        return Ok(Cow::Borrowed(&response.body));
+    };
+    let Some(mut decoder) = BodyDecoder::for_encoding(&encoding) else {
+        return Ok(Cow::Borrowed(&response.body));
+    };
+    decoder
+        .write(&response.body)
+        .and_then(|_| decoder.finish())
+        .map(Cow::Owned)
+        .map_err(|e| {
+            DownloadError::Decompression {
+                encoding,
+                reason: e.to_string(),
+            }
+            .into()
+        })
+}
+
+fn header_str(response: &Response, name: http::header::HeaderName) -> Option<String> {
+    response
+        .headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+fn is_json_content_type(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    essence == "application/json" || essence == "text/json" || essence.ends_with("+json")
+}
+
+/// Returns the start of `body` as lossy UTF-8 for error messages.
+pub(crate) fn snippet(body: &[u8]) -> String {
+    let end = body.len().min(SNIPPET_LEN);
+    String::from_utf8_lossy(&body[..end]).into_owned()
+}
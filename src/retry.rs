@@ -0,0 +1,207 @@
+//! Retry policy for failed downloads.
+//!
+//! A `RetryPolicy` decides whether a failed attempt is retried and how long to
+//! wait before the next one. Individual requests can override the downloader's
+//! policy through `meta`:
+//!
+//! - `max_retries`: number of retries after the first attempt (`0` disables retries)
+//! - `retry_backoff_ms`: base backoff in milliseconds
+
+use http::{HeaderValue, StatusCode};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+/// Meta key overriding `RetryPolicy::max_retries` for a single request.
+pub const META_MAX_RETRIES: &str = "max_retries";
+/// Meta key overriding `RetryPolicy::backoff` (in milliseconds) for a single request.
+pub const META_RETRY_BACKOFF_MS: &str = "retry_backoff_ms";
+
+/// Randomization applied to backoff delays.
+///
+/// Randomizing delays keeps clients that failed together from retrying in
+/// lockstep. With `exp = backoff * 2^n` for retry `n`:
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Wait exactly `exp`.
+    None,
+    /// Wait a random delay in `[0, exp)`.
+    #[default]
+    Full,
+    /// Wait `exp / 2` plus a random delay in `[0, exp / 2)`.
+    Equal,
+    /// Wait a random delay in `[backoff, previous * 3)`, AWS's "decorrelated
+    /// jitter", which grows from the previous delay instead of the attempt number.
+    Decorrelated,
+}
+
+/// What to do when a response has a given status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAction {
+    /// Retry after the policy's backoff delay.
+    Backoff,
+    /// Retry after the delay in the response's `Retry-After` header, falling
+    /// back to the backoff delay when the header is missing or invalid.
+    RetryAfter,
+    /// Don't retry; return the response as is.
+    Never,
+}
+
+/// Controls how many times, and how eagerly, failed downloads are retried.
+///
+/// Connection errors, timeouts and `5xx` responses are retried; per-status
+/// rules set with `retry_status` override that for individual codes. The delay
+/// before retry `n` (starting at 0) is based on `backoff * 2^n`, randomized
+/// according to `jitter` and capped at `max_backoff`. Retrying stops once
+/// `max_retries` is reached or the next wait would take the cumulative waiting
+/// time past `max_total_retry_time`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Base delay before the first retry.
+    pub backoff: Duration,
+    /// Randomization applied to each delay.
+    pub jitter: Jitter,
+    /// Per-status overrides of the default "retry all `5xx`" rule.
+    pub status_actions: BTreeMap<u16, RetryAction>,
+    /// Upper bound on each backoff delay; `None` for no cap.
+    pub max_backoff: Option<Duration>,
+    /// Upper bound on the total time spent waiting between attempts; `None` for no budget.
+    pub max_total_retry_time: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy with the given retry count and base backoff.
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            backoff,
+            jitter: Jitter::default(),
+            status_actions: BTreeMap::new(),
+            max_backoff: None,
+            max_total_retry_time: None,
+        }
+    }
+
+    /// Sets how responses with `status` are treated, e.g.
+    /// `.retry_status(429, RetryAction::RetryAfter).retry_status(501, RetryAction::Never)`.
+    pub fn retry_status(mut self, status: u16, action: RetryAction) -> Self {
+        self.status_actions.insert(status, action);
+        self
+    }
+
+    /// Sets the jitter strategy.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Caps each backoff delay at `max`.
+    ///
+    /// Delays requested by a server through `Retry-After` are capped as well,
+    /// so a server can't hold a download for longer than `max` per retry.
+    pub fn with_max_backoff(mut self, max: Duration) -> Self {
+        self.max_backoff = Some(max);
+        self
+    }
+
+    /// Gives up once the cumulative wait between attempts would exceed `budget`.
+    pub fn with_max_total_retry_time(mut self, budget: Duration) -> Self {
+        self.max_total_retry_time = Some(budget);
+        self
+    }
+
+    /// Returns whether waiting `next` more, after `waited` so far, stays within the budget.
+    pub fn within_budget(&self, waited: Duration, next: Duration) -> bool {
+        self.max_total_retry_time
+            .is_none_or(|budget| waited.saturating_add(next) <= budget)
+    }
+
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Returns the un-jittered delay before retry number `retry` (0-based), capped at `max_backoff`.
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let exp = self.backoff.saturating_mul(2u32.saturating_pow(retry));
+        match self.max_backoff {
+            Some(max) => exp.min(max),
+            None => exp,
+        }
+    }
+
+    /// Returns the jittered delay before retry number `retry` (0-based), capped at `max_backoff`.
+    ///
+    /// `previous` is the delay used before the previous retry, or zero for the
+    /// first; only `Jitter::Decorrelated` depends on it.
+    pub fn next_delay(&self, retry: u32, previous: Duration) -> Duration {
+        let exp = self.delay_for(retry);
+        let random = rand::random::<f64>();
+        let delay = match self.jitter {
+            Jitter::None => exp,
+            Jitter::Full => exp.mul_f64(random),
+            Jitter::Equal => {
+                let half = exp / 2;
+                half + half.mul_f64(random)
+            }
+            Jitter::Decorrelated => {
+                let upper = previous.saturating_mul(3).max(self.backoff);
+                self.backoff + (upper - self.backoff).mul_f64(random)
+            }
+        };
+        match self.max_backoff {
+            Some(max) => delay.min(max),
+            None => delay,
+        }
+    }
+
+    /// Returns the action for a response with `status`.
+    pub fn action_for(&self, status: StatusCode) -> RetryAction {
+        match self.status_actions.get(&status.as_u16()) {
+            Some(action) => *action,
+            None if status.is_server_error() => RetryAction::Backoff,
+            None => RetryAction::Never,
+        }
+    }
+
+    /// Returns whether a response with `status` should be retried.
+    pub fn should_retry_status(&self, status: StatusCode) -> bool {
+        self.action_for(status) != RetryAction::Never
+    }
+
+    /// Returns a copy of this policy with `max_retries` / `retry_backoff_ms` taken from `meta`.
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn with_overrides<F>(&self, lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<u64>,
+    {
+        let mut policy = self.clone();
+        if let Some(max_retries) = lookup(META_MAX_RETRIES) {
+            policy.max_retries = u32::try_from(max_retries).unwrap_or(u32::MAX);
+        }
+        if let Some(backoff_ms) = lookup(META_RETRY_BACKOFF_MS) {
+            policy.backoff = Duration::from_millis(backoff_ms);
+        }
+        policy
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Parses a `Retry-After` value, given either in seconds or as an HTTP date.
+pub fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
@@ -0,0 +1,99 @@
+//! Request signing hooks.
+//!
+//! A `RequestSigner` runs as the last step before a request is sent, after
+//! every header the downloader adds, so the signature covers the final form
+//! of the request.
+
+use crate::hash::to_hex;
+use hmac::{Hmac, Mac};
+use http::{HeaderName, HeaderValue};
+use sha2::Sha256;
+use spider_util::error::SpiderError;
+use spider_util::request::{Body, Request};
+
+/// Signs outgoing requests, typically by adding an authentication header.
+pub trait RequestSigner: Send + Sync {
+    /// Signs `request` in place.
+    fn sign(&self, request: &mut Request) -> Result<(), SpiderError>;
+}
+
+/// Signs requests with HMAC-SHA256 and stores the hex digest in a header.
+///
+/// The signed message is the method, the path with query, and the body, each
+/// separated by a newline:
+///
+/// ```text
+/// POST
+/// /v1/items?page=2
+/// {"name":"widget"}
+/// ```
+///
+/// JSON bodies are signed in their serialized form and byte bodies as-is. Form
+/// bodies are not covered since their encoding order isn't stable.
+#[derive(Clone)]
+pub struct HmacSha256Signer {
+    key: Vec<u8>,
+    header: HeaderName,
+}
+
+impl HmacSha256Signer {
+    /// Creates a signer writing the signature to `X-Signature`.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        HmacSha256Signer {
+            key: key.into(),
+            header: HeaderName::from_static("x-signature"),
+        }
+    }
+
+    /// Writes the signature to `header` instead of `X-Signature`.
+    pub fn with_header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Returns the hex-encoded signature of `message`.
+    pub fn signature(&self, message: &[u8]) -> String {
+        // HMAC accepts keys of any length, so this cannot fail.
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(message);
+        to_hex(&mac.finalize().into_bytes())
+    }
+}
+
+impl RequestSigner for HmacSha256Signer {
+    fn sign(&self, request: &mut Request) -> Result<(), SpiderError> {
+        let mut message = Vec::new();
+        message.extend_from_slice(request.method.as_str().as_bytes());
+        message.push(b'\n');
+        message.extend_from_slice(request.url.path().as_bytes());
+        if let Some(query) = request.url.query() {
+            message.push(b'?');
+            message.extend_from_slice(query.as_bytes());
+        }
+        message.push(b'\n');
+        match &request.body {
+            Some(Body::Json(value)) => message.extend_from_slice(
+                &serde_json::to_vec(value).map_err(|e| SpiderError::GeneralError(e.to_string()))?,
+            ),
+            Some(Body::Bytes(bytes)) => message.extend_from_slice(bytes),
+            _ => {}
+        }
+
+        let value = HeaderValue::from_str(&self.signature(&message))
+            .expect("hex digits are a valid header value");
+        request.headers.insert(self.header.clone(), value);
+        Ok(())
+    }
+}
+
+/// Compares two byte strings in time that depends only on their lengths.
+///
+/// Use this when checking signatures so the comparison doesn't leak how many
+/// leading bytes matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,58 @@
+//! Detection of "soft 404s": missing pages served with a success status.
+
+use http::{HeaderMap, StatusCode};
+
+/// Number of leading body bytes passed to a `SoftNotFoundDetector`.
+pub(crate) const SOFT_404_PREFIX_LEN: usize = 16 * 1024;
+
+/// Decides whether a response is a missing page disguised as a success.
+///
+/// Flagged responses carry `soft_404: true` in their meta.
+pub trait SoftNotFoundDetector: Send + Sync {
+    /// Returns whether the response is a soft 404.
+    ///
+    /// `body_prefix` holds up to the first 16 KiB of the body; it is the whole
+    /// body when shorter than that.
+    fn is_soft_404(&self, status: StatusCode, headers: &HeaderMap, body_prefix: &[u8]) -> bool;
+}
+
+/// The default soft-404 heuristic: a short successful body mentioning a missing page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftNotFoundHeuristic {
+    /// Bodies this long or longer are never flagged. Defaults to 8 KiB.
+    pub max_body_len: usize,
+    /// Lowercase phrases of which one must appear in the body.
+    pub phrases: Vec<String>,
+}
+
+impl Default for SoftNotFoundHeuristic {
+    fn default() -> Self {
+        SoftNotFoundHeuristic {
+            max_body_len: 8 * 1024,
+            phrases: [
+                "page not found",
+                "404 not found",
+                "error 404",
+                "does not exist",
+                "no longer available",
+                "could not be found",
+                "couldn't find the page",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        }
+    }
+}
+
+impl SoftNotFoundDetector for SoftNotFoundHeuristic {
+    fn is_soft_404(&self, status: StatusCode, _headers: &HeaderMap, body_prefix: &[u8]) -> bool {
+        if !status.is_success() || body_prefix.len() >= self.max_body_len {
+            return false;
+        }
+        let text = String::from_utf8_lossy(body_prefix).to_lowercase();
+        self.phrases
+            .iter()
+            .any(|phrase| text.contains(phrase.as_str()))
+    }
+}
@@ -0,0 +1,108 @@
+//! Streaming responses whose body is consumed as it arrives.
+
+use crate::error::DownloadError;
+use crate::multipart::{self, MultipartPart};
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use http::{HeaderMap, StatusCode};
+use serde_json::Value;
+use spider_util::error::SpiderError;
+use std::pin::Pin;
+use url::Url;
+
+/// A stream of body chunks.
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, SpiderError>> + Send>>;
+
+/// A response whose body has not been read yet.
+pub struct StreamingResponse {
+    /// Final URL after redirects.
+    pub url: Url,
+    /// Response status.
+    pub status: StatusCode,
+    /// Response headers.
+    pub headers: HeaderMap,
+    /// URL of the request that produced this response.
+    pub request_url: Url,
+    /// The body, yielded chunk by chunk.
+    pub body: BodyStream,
+}
+
+impl StreamingResponse {
+    /// Parses the body as newline-delimited JSON, yielding one value per line.
+    ///
+    /// Lines split across chunks are buffered until complete, blank lines are
+    /// skipped and a trailing line without a newline is parsed at the end of the
+    /// stream. A transport error ends the stream after being yielded.
+    pub fn ndjson(self) -> impl Stream<Item = Result<Value, SpiderError>> + Send {
+        stream::unfold(
+            (self.body, Vec::new(), false),
+            |(mut body, mut buf, mut done)| async move {
+                loop {
+                    if let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = buf.drain(..=pos).collect();
+                        let line = line.trim_ascii();
+                        if line.is_empty() {
+                            continue;
+                        }
+                        let value = parse_line(line);
+                        return Some((value, (body, buf, done)));
+                    }
+                    if done {
+                        let line = std::mem::take(&mut buf);
+                        let line = line.trim_ascii();
+                        if line.is_empty() {
+                            return None;
+                        }
+                        let value = parse_line(line);
+                        return Some((value, (body, buf, done)));
+                    }
+                    match body.next().await {
+                        Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+                        Some(Err(e)) => {
+                            buf.clear();
+                            return Some((Err(e), (body, buf, true)));
+                        }
+                        None => done = true,
+                    }
+                }
+            },
+        )
+    }
+
+    /// Splits a `multipart/*` body, e.g. `multipart/x-mixed-replace`, into its parts as they arrive.
+    ///
+    /// The boundary is taken from `Content-Type`; a response of another type,
+    /// or without a boundary, fails with `DownloadError::InvalidResponse`. Each
+    /// part is yielded once complete, so a push stream yields parts for as long
+    /// as the server keeps the connection open. A body that ends inside a part
+    /// yields an error; a transport error ends the stream after being yielded.
+    pub fn multipart(
+        self,
+    ) -> Result<impl Stream<Item = Result<MultipartPart, SpiderError>> + Send, SpiderError> {
+        let Some(boundary) = multipart::boundary(&self.headers) else {
+            let content_type = self
+                .headers
+                .get(http::header::CONTENT_TYPE)
+                .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+                .unwrap_or_default();
+            return Err(DownloadError::InvalidResponse {
+                reason: format!(
+                    "expected a multipart body with a boundary, got Content-Type {:?}",
+                    content_type
+                ),
+            }
+            .into());
+        };
+        Ok(multipart::parts(self.body, &boundary))
+    }
+}
+
+fn parse_line(line: &[u8]) -> Result<Value, SpiderError> {
+    serde_json::from_slice(line).map_err(|e| {
+        DownloadError::Decode {
+            reason: e.to_string(),
+            snippet: crate::response_ext::snippet(line),
+        }
+        .into()
+    })
+}
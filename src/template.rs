@@ -0,0 +1,101 @@
+//! Templates for generating many near-identical requests.
+//!
+//! ```rust,ignore
+//! let template = RequestTemplate::new("https://api.example.com/items/{id}?page={page}")
+//!     .header(ACCEPT, HeaderValue::from_static("application/json"));
+//! let params = (1..=100).map(|id| HashMap::from([
+//!     ("id".to_string(), id.to_string()),
+//!     ("page".to_string(), "1".to_string()),
+//! ]));
+//! let responses = downloader.download_template(&template, params, 8).await;
+//! ```
+
+use http::{HeaderMap, HeaderName, HeaderValue, Method};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use spider_util::error::SpiderError;
+use spider_util::request::{Body, Request};
+use std::collections::HashMap;
+use url::Url;
+
+/// Characters escaped in substituted values: everything but RFC 3986 unreserved characters.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A request with `{placeholders}` in its URL, instantiated once per set of parameters.
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    url: String,
+    method: Method,
+    headers: HeaderMap,
+    body: Option<Body>,
+}
+
+impl RequestTemplate {
+    /// Creates a `GET` template for `url`, whose `{name}` placeholders are filled in later.
+    pub fn new(url: impl Into<String>) -> Self {
+        RequestTemplate {
+            url: url.into(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    /// Sets the method of generated requests.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Adds a header to generated requests.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Sets the body of generated requests.
+    pub fn body(mut self, body: Body) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Returns the URL template.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Builds a request, replacing each `{name}` with the percent-encoded `params[name]`.
+    ///
+    /// Fails if a placeholder has no value or the resulting URL is invalid.
+    pub fn instantiate(&self, params: &HashMap<String, String>) -> Result<Request, SpiderError> {
+        let mut url = String::with_capacity(self.url.len());
+        let mut rest = self.url.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 1..start + len];
+            let value = params.get(name).ok_or_else(|| {
+                SpiderError::GeneralError(format!(
+                    "No value for placeholder {{{}}} in {}",
+                    name, self.url
+                ))
+            })?;
+            url.push_str(&rest[..start]);
+            url.extend(utf8_percent_encode(value, COMPONENT));
+            rest = &rest[start + len + 1..];
+        }
+        url.push_str(rest);
+
+        let url = Url::parse(&url)
+            .map_err(|e| SpiderError::GeneralError(format!("Invalid URL {}: {}", url, e)))?;
+        let mut request = Request::new(url);
+        request.method = self.method.clone();
+        request.headers = self.headers.clone();
+        request.body = self.body.clone();
+        Ok(request)
+    }
+}
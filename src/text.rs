@@ -0,0 +1,28 @@
+//! Decoding of response bodies into text.
+
+use crate::error::DownloadError;
+use spider_util::error::SpiderError;
+
+/// How invalid UTF-8 in a body is handled when decoding it as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDecodePolicy {
+    /// Replace invalid sequences with U+FFFD.
+    #[default]
+    Lossy,
+    /// Fail with `DownloadError::InvalidUtf8` at the first invalid sequence.
+    Strict,
+}
+
+/// Decodes `bytes` as UTF-8 according to `policy`.
+pub fn decode_text(bytes: &[u8], policy: TextDecodePolicy) -> Result<String, SpiderError> {
+    match policy {
+        TextDecodePolicy::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        TextDecodePolicy::Strict => match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(text.to_string()),
+            Err(e) => Err(DownloadError::InvalidUtf8 {
+                offset: e.valid_up_to(),
+            }
+            .into()),
+        },
+    }
+}
@@ -0,0 +1,127 @@
+//! TLS protocol versions and per-host TLS settings.
+//!
+//! `TlsVersion` bounds the protocol versions every client of the downloader
+//! negotiates, through `min_tls_version` and `max_tls_version`.
+//!
+//! Most of a crawl should use the downloader's TLS defaults, but a single
+//! misconfigured origin may need an older protocol version or a relaxed name
+//! check. A `TlsConfig` registered for a host applies only to the clients built
+//! for that host; settings it leaves unset fall back to the downloader's.
+//!
+//! The reqwest backend doesn't expose cipher suite selection, so ciphers can't
+//! be overridden. TLS 1.0 and 1.1 are only available with the `native-tls`
+//! backend; with `rustls-tls`, a configuration allowing nothing newer than TLS
+//! 1.1 makes every request to the host fail.
+
+use reqwest::ClientBuilder;
+use std::fmt;
+
+/// A TLS protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.0.
+    Tls1_0,
+    /// TLS 1.1.
+    Tls1_1,
+    /// TLS 1.2.
+    Tls1_2,
+    /// TLS 1.3.
+    Tls1_3,
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let version = match self {
+            TlsVersion::Tls1_0 => "1.0",
+            TlsVersion::Tls1_1 => "1.1",
+            TlsVersion::Tls1_2 => "1.2",
+            TlsVersion::Tls1_3 => "1.3",
+        };
+        write!(f, "TLS {}", version)
+    }
+}
+
+impl TlsVersion {
+    pub(crate) fn to_reqwest(self) -> reqwest::tls::Version {
+        match self {
+            TlsVersion::Tls1_0 => reqwest::tls::Version::TLS_1_0,
+            TlsVersion::Tls1_1 => reqwest::tls::Version::TLS_1_1,
+            TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+/// TLS settings overriding the downloader's for one host.
+///
+/// Fields left as `None` keep the downloader-wide value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// Oldest protocol version offered.
+    pub min_version: Option<TlsVersion>,
+    /// Newest protocol version offered.
+    pub max_version: Option<TlsVersion>,
+    /// Whether the server name is sent (SNI).
+    pub sni: Option<bool>,
+    /// Whether certificates issued for another host name are accepted.
+    pub accept_invalid_hostnames: Option<bool>,
+    /// Whether invalid certificates are accepted altogether. Dangerous.
+    pub accept_invalid_certs: Option<bool>,
+}
+
+impl TlsConfig {
+    /// Creates a configuration that overrides nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the oldest protocol version offered.
+    pub fn min_version(mut self, version: TlsVersion) -> Self {
+        self.min_version = Some(version);
+        self
+    }
+
+    /// Sets the newest protocol version offered.
+    pub fn max_version(mut self, version: TlsVersion) -> Self {
+        self.max_version = Some(version);
+        self
+    }
+
+    /// Controls whether the server name is sent (SNI).
+    pub fn sni(mut self, enabled: bool) -> Self {
+        self.sni = Some(enabled);
+        self
+    }
+
+    /// Accepts certificates issued for another host name, still verifying the chain.
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.accept_invalid_hostnames = Some(accept);
+        self
+    }
+
+    /// Accepts any certificate, including self-signed and expired ones.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = Some(accept);
+        self
+    }
+
+    /// Applies the settings that are set on top of `builder`.
+    pub(crate) fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(version) = self.min_version {
+            builder = builder.min_tls_version(version.to_reqwest());
+        }
+        if let Some(version) = self.max_version {
+            builder = builder.max_tls_version(version.to_reqwest());
+        }
+        if let Some(sni) = self.sni {
+            builder = builder.tls_sni(sni);
+        }
+        if let Some(accept) = self.accept_invalid_hostnames {
+            builder = builder.danger_accept_invalid_hostnames(accept);
+        }
+        if let Some(accept) = self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(accept);
+        }
+        builder
+    }
+}
@@ -1,12 +1,37 @@
 //! Traits for HTTP downloaders in the `spider-lib` framework.
 
+use crate::StreamingResponse;
+use crate::cost::RequestCost;
+use crate::hash::{HashAlgo, to_hex};
+use crate::meta::META_CONTENT_HASH;
+use crate::request_ext::apply_query;
+use crate::template::RequestTemplate;
+use crate::text::decode_text;
+use crate::{Clock, RetryPolicy, TextDecodePolicy};
 use async_trait::async_trait;
 use bytes::Bytes;
-use http::StatusCode;
+use futures_util::{StreamExt, stream};
+use http::{HeaderMap, StatusCode};
+use serde_json::Value;
 use spider_util::error::SpiderError;
 use spider_util::request::Request;
 use spider_util::response::Response;
+use std::collections::HashMap;
 use std::time::Duration;
+use url::Url;
+
+/// Timeout applied by `SimpleHttpClient::health_check`.
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How `Downloader::download_many` handles failed requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchMode {
+    /// Download every request and return all results.
+    #[default]
+    ContinueOnError,
+    /// Stop at the first error, cancelling the requests still in flight.
+    FailFast,
+}
 
 /// A simple HTTP client trait for fetching web content.
 #[async_trait]
@@ -17,6 +42,87 @@ pub trait SimpleHttpClient: Send + Sync {
         url: &str,
         timeout: Duration,
     ) -> Result<(StatusCode, Bytes), SpiderError>;
+
+    /// Sends `form` URL-encoded in a `POST` body, with `headers` added to the request.
+    ///
+    /// Used for token endpoints such as `OAuth2Provider`'s. The default
+    /// implementation fails, since `get_text` alone can't express a `POST`.
+    async fn post_form(
+        &self,
+        url: &str,
+        _form: &[(&str, &str)],
+        _headers: HeaderMap,
+        _timeout: Duration,
+    ) -> Result<(StatusCode, Bytes), SpiderError> {
+        Err(SpiderError::GeneralError(format!(
+            "Cannot POST to {}: this client only supports GET",
+            url
+        )))
+    }
+
+    /// Checks that `url` is reachable, returning only the response status.
+    ///
+    /// Meant for readiness probes: it uses a short timeout of
+    /// `HEALTH_CHECK_TIMEOUT`. The default implementation issues a `GET`
+    /// through `get_text` and discards the body; implementations may use a
+    /// cheaper request such as `HEAD`.
+    async fn health_check(&self, url: &str) -> Result<StatusCode, SpiderError> {
+        let (status, _) = self.get_text(url, HEALTH_CHECK_TIMEOUT).await?;
+        Ok(status)
+    }
+
+    /// Fetches a URL and decodes its body as UTF-8 text according to `policy`.
+    ///
+    /// Unlike `get_text`, which returns raw bytes, this yields a `String`.
+    async fn get_decoded_text(
+        &self,
+        url: &str,
+        timeout: Duration,
+        policy: TextDecodePolicy,
+    ) -> Result<(StatusCode, String), SpiderError> {
+        let (status, body) = self.get_text(url, timeout).await?;
+        Ok((status, decode_text(&body, policy)?))
+    }
+
+    /// Fetches a URL like `get_text`, making up to `attempts` attempts in total.
+    ///
+    /// Transient errors, as told by `is_transient_error`, and `5xx` responses
+    /// are retried with jittered exponential backoff from a 500ms base, waiting
+    /// on `clock`. Other errors are returned at once, and the last result is
+    /// returned once attempts run out.
+    async fn get_text_retry(
+        &self,
+        url: &str,
+        timeout: Duration,
+        attempts: u32,
+        clock: &dyn Clock,
+    ) -> Result<(StatusCode, Bytes), SpiderError> {
+        let policy = RetryPolicy::new(attempts.saturating_sub(1), Duration::from_millis(500));
+        let mut retry = 0;
+        let mut delay = Duration::ZERO;
+        loop {
+            let result = self.get_text(url, timeout).await;
+            let retryable = match &result {
+                Ok((status, _)) => policy.should_retry_status(*status),
+                Err(e) => self.is_transient_error(e),
+            };
+            if !retryable || retry >= policy.max_retries {
+                return result;
+            }
+            delay = policy.next_delay(retry, delay);
+            clock.sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    /// Returns whether a `get_text` error is worth retrying.
+    ///
+    /// The default treats transport failures, reported as
+    /// `SpiderError::ReqwestError`, as transient and everything else, such as
+    /// an undecodable body, as permanent.
+    fn is_transient_error(&self, error: &SpiderError) -> bool {
+        matches!(error, SpiderError::ReqwestError(_))
+    }
 }
 
 /// A trait for HTTP downloaders that can fetch web pages and apply middleware
@@ -31,5 +137,147 @@ pub trait Downloader: Send + Sync + 'static {
     /// Returns a reference to the underlying HTTP client.
     fn client(&self) -> &Self::Client;
 
-}
+    /// Download a web page, exposing the body as a stream of chunks.
+    ///
+    /// The default implementation buffers the whole body through `download` and
+    /// yields it as a single chunk; check `supports_true_streaming` to know
+    /// which behavior an implementation provides.
+    async fn download_stream(&self, request: Request) -> Result<StreamingResponse, SpiderError> {
+        let response = self.download(request).await?;
+        Ok(StreamingResponse {
+            url: response.url,
+            status: response.status,
+            headers: response.headers,
+            request_url: response.request_url,
+            body: Box::pin(stream::once(async move { Ok(response.body) })),
+        })
+    }
+
+    /// Download a web page and hash its body with `algo`.
+    ///
+    /// The digest is returned alongside the response and stored as hex in
+    /// `Response.meta` under `content_hash`. It covers the body as returned by
+    /// `download`, i.e. after any decompression.
+    ///
+    /// The body is hashed in a second pass once `download` has buffered it.
+    /// Since the whole body is held in memory for the `Response` either way,
+    /// hashing while it is read would save no memory, only that pass. To hash a
+    /// body too large to buffer, feed the chunks of `download_stream` to a
+    /// hasher instead.
+    async fn download_with_hash(
+        &self,
+        request: Request,
+        algo: HashAlgo,
+    ) -> Result<(Response, Vec<u8>), SpiderError> {
+        let response = self.download(request).await?;
+        let digest = algo.digest(&response.body);
+        response
+            .meta
+            .insert(META_CONTENT_HASH.into(), Value::String(to_hex(&digest)));
+        Ok((response, digest))
+    }
+
+    /// Downloads one request per parameter map generated from `template`.
+    ///
+    /// At most `concurrency` downloads run at once. Results are returned in the
+    /// order of `params`; a parameter map that doesn't fit the template yields
+    /// an error in its slot without affecting the others.
+    async fn download_template<I>(
+        &self,
+        template: &RequestTemplate,
+        params: I,
+        concurrency: usize,
+    ) -> Vec<Result<Response, SpiderError>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = HashMap<String, String>> + Send,
+        I::IntoIter: Send,
+    {
+        stream::iter(params)
+            .map(|params| async move {
+                let request = template.instantiate(&params)?;
+                self.download(request).await
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
 
+    /// Estimates how expensive downloading `request` would be, for scheduling.
+    ///
+    /// The estimate is advisory: schedulers may use it to weight or budget
+    /// requests, and nothing is enforced. The default returns
+    /// `RequestCost::NEUTRAL`; implementations can account for proxies, expected
+    /// body sizes or how often the target host has failed.
+    fn estimated_cost(&self, _request: &Request) -> RequestCost {
+        RequestCost::NEUTRAL
+    }
+
+    /// Downloads `requests` with at most `concurrency` in flight at once.
+    ///
+    /// With `BatchMode::ContinueOnError` every request is downloaded and the
+    /// results are returned in the order of `requests`. With
+    /// `BatchMode::FailFast` the first error to occur is returned instead:
+    /// requests still in flight are cancelled and the rest are never sent.
+    async fn download_many<I>(
+        &self,
+        requests: I,
+        concurrency: usize,
+        mode: BatchMode,
+    ) -> Result<Vec<Result<Response, SpiderError>>, SpiderError>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Request> + Send,
+        I::IntoIter: Send,
+    {
+        let mut pending = stream::iter(requests.into_iter().enumerate())
+            .map(|(index, request)| async move { (index, self.download(request).await) })
+            .buffer_unordered(concurrency.max(1));
+        let mut slots: Vec<Option<Result<Response, SpiderError>>> = Vec::new();
+        while let Some((index, result)) = pending.next().await {
+            let result = match (mode, result) {
+                // Dropping `pending` cancels the downloads still in flight.
+                (BatchMode::FailFast, Err(e)) => return Err(e),
+                (_, result) => result,
+            };
+            if slots.len() <= index {
+                slots.resize_with(index + 1, || None);
+            }
+            slots[index] = Some(result);
+        }
+        Ok(slots
+            .into_iter()
+            .map(|slot| slot.expect("every request yields a result"))
+            .collect())
+    }
+
+    /// Checks whether `download` would accept a request for `url`, without sending anything.
+    ///
+    /// Lets a scheduler drop URLs that would be rejected immediately instead
+    /// of enqueuing them. Only checks made before sending are covered; the
+    /// download itself can still fail. The default accepts every URL.
+    fn would_allow(&self, _url: &Url) -> Result<(), SpiderError> {
+        Ok(())
+    }
+
+    /// Returns the fingerprint of `request` as it will be sent.
+    ///
+    /// Downloaders rewrite requests before sending them, e.g. merging the
+    /// `RequestExt::with_query` parameters into the URL, so `Request::fingerprint`
+    /// on the request as built can match requests that are sent differently.
+    /// Wrappers such as `CachingDownloader` and `ReplayDownloader` key on this
+    /// instead. The default drops the fragment and merges the query parameters;
+    /// implementations that rewrite more should fingerprint the rewritten form.
+    fn fingerprint(&self, request: &Request) -> String {
+        let mut request = request.clone();
+        request.url.set_fragment(None);
+        apply_query(&mut request, false);
+        request.fingerprint()
+    }
+
+    /// Returns whether `download_stream` yields the body as it arrives rather
+    /// than buffering it first.
+    fn supports_true_streaming(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,48 @@
+//! Streamed request bodies with a known length.
+
+use bytes::Bytes;
+use futures_util::Stream;
+use std::pin::Pin;
+
+/// A request body streamed from `stream`, declared to be `len` bytes long.
+///
+/// The length is sent as `Content-Length` and used to report upload progress.
+pub struct UploadBody {
+    /// The body chunks.
+    pub stream: Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>,
+    /// Total body length in bytes.
+    pub len: u64,
+}
+
+impl UploadBody {
+    /// Creates an upload body from a stream and its total length.
+    pub fn new<S>(stream: S, len: u64) -> Self
+    where
+        S: Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    {
+        UploadBody {
+            stream: Box::pin(stream),
+            len,
+        }
+    }
+}
+
+/// Progress of a streamed upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes handed to the connection so far.
+    pub sent: u64,
+    /// Declared total length.
+    pub total: u64,
+}
+
+impl UploadProgress {
+    /// Returns the completed fraction as a percentage between 0 and 100.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            (self.sent as f64 / self.total as f64 * 100.0).min(100.0)
+        }
+    }
+}
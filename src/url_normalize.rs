@@ -0,0 +1,108 @@
+//! URL canonicalization applied before fingerprinting and sending requests.
+//!
+//! Different spellings of the same resource (host case, explicit default ports,
+//! duplicate slashes, fragments) otherwise produce different fingerprints and
+//! separate downloads. Sorting query parameters is kept separate because some
+//! servers treat parameter order as significant.
+
+use url::Url;
+
+/// Canonicalizes `url` in place.
+///
+/// - lowercases the host
+/// - removes the port if it is the scheme's default
+/// - collapses repeated slashes in the path
+/// - uppercases the hex digits of percent-escapes in the path and query
+/// - removes the fragment
+/// - sorts query parameters by key (stable for repeated keys) if `sort_query` is set
+///
+/// Trailing slashes are left untouched since `/a` and `/a/` may be different resources.
+pub fn normalize_url(url: &mut Url, sort_query: bool) {
+    if let Some(host) = url.host_str() {
+        let lowered = host.to_ascii_lowercase();
+        if lowered != host {
+            let _ = url.set_host(Some(&lowered));
+        }
+    }
+
+    if url.port().is_some() && url.port() == default_port(url.scheme()) {
+        let _ = url.set_port(None);
+    }
+
+    if url.path().contains("//") {
+        let mut path = String::with_capacity(url.path().len());
+        let mut prev_slash = false;
+        for c in url.path().chars() {
+            if c == '/' && prev_slash {
+                continue;
+            }
+            prev_slash = c == '/';
+            path.push(c);
+        }
+        url.set_path(&path);
+    }
+
+    if let Some(path) = uppercase_escapes(url.path()) {
+        url.set_path(&path);
+    }
+    if let Some(query) = url.query().and_then(uppercase_escapes) {
+        url.set_query(Some(&query));
+    }
+
+    url.set_fragment(None);
+
+    if sort_query && url.query().is_some() {
+        let mut pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+}
+
+/// Returns `s` (ASCII, as URL components are) with `%2f`-style escapes uppercased,
+/// or `None` if there are none to change.
+fn uppercase_escapes(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let needs_change = bytes.windows(3).any(|w| {
+        w[0] == b'%'
+            && w[1].is_ascii_hexdigit()
+            && w[2].is_ascii_hexdigit()
+            && (w[1].is_ascii_lowercase() || w[2].is_ascii_lowercase())
+    });
+    if !needs_change {
+        return None;
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            out.push('%');
+            out.push(bytes[i + 1].to_ascii_uppercase() as char);
+            out.push(bytes[i + 2].to_ascii_uppercase() as char);
+            i += 3;
+        } else {
+            out.push(bytes[i] as char);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
@@ -0,0 +1,72 @@
+//! Post-download response checks.
+//!
+//! A `ResponseValidator` inspects every response the downloader produces and
+//! can reject it with an error, centralizing checks such as "the body must not
+//! be empty" that would otherwise be repeated throughout a pipeline.
+
+use crate::error::DownloadError;
+use http::{HeaderName, StatusCode};
+use spider_util::error::SpiderError;
+use spider_util::response::Response;
+
+/// Accepts or rejects a downloaded response.
+pub trait ResponseValidator: Send + Sync {
+    /// Returns an error if `response` should not be handed to the caller.
+    fn validate(&self, response: &Response) -> Result<(), SpiderError>;
+}
+
+/// Rejects responses whose body is shorter than the given number of bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinBodySize(pub usize);
+
+impl ResponseValidator for MinBodySize {
+    fn validate(&self, response: &Response) -> Result<(), SpiderError> {
+        if response.body.len() < self.0 {
+            return Err(DownloadError::InvalidResponse {
+                reason: format!(
+                    "body of {} bytes is shorter than {} bytes",
+                    response.body.len(),
+                    self.0
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Rejects responses whose status is not in the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedStatuses(pub Vec<StatusCode>);
+
+impl ResponseValidator for AllowedStatuses {
+    fn validate(&self, response: &Response) -> Result<(), SpiderError> {
+        if !self.0.contains(&response.status) {
+            return Err(DownloadError::InvalidResponse {
+                reason: format!("status {} is not allowed", response.status),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Rejects responses missing any of the listed headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredHeaders(pub Vec<HeaderName>);
+
+impl ResponseValidator for RequiredHeaders {
+    fn validate(&self, response: &Response) -> Result<(), SpiderError> {
+        if let Some(missing) = self
+            .0
+            .iter()
+            .find(|name| !response.headers.contains_key(*name))
+        {
+            return Err(DownloadError::InvalidResponse {
+                reason: format!("missing {} header", missing),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
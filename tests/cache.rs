@@ -0,0 +1,43 @@
+mod common;
+
+use common::{response, serve};
+use spider_downloader::{
+    CachingDownloader, Downloader, Request, RequestExt, ReqwestClientDownloader,
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn query_parameters_are_part_of_the_key() {
+    let server = serve(vec![response("200 OK", &[], b"page")]).await;
+    let cache = CachingDownloader::new(ReqwestClientDownloader::new(), Duration::from_secs(60));
+    let page = |n: &str| {
+        Request::new(server.url.join("list").unwrap())
+            .with_query(&[("page", n)])
+            .unwrap()
+    };
+
+    cache.download(page("1")).await.unwrap();
+    cache.download(page("2")).await.unwrap();
+    let again = cache.download(page("1")).await.unwrap();
+
+    let targets: Vec<String> = server.received().into_iter().map(|r| r.target).collect();
+    assert_eq!(targets, ["/list?page=1", "/list?page=2"]);
+    assert_eq!(&again.body[..], b"page");
+}
+
+#[tokio::test]
+async fn fragments_share_a_key() {
+    let server = serve(vec![response("200 OK", &[], b"page")]).await;
+    let cache = CachingDownloader::new(ReqwestClientDownloader::new(), Duration::from_secs(60));
+
+    cache
+        .download(Request::new(server.url.join("doc#intro").unwrap()))
+        .await
+        .unwrap();
+    cache
+        .download(Request::new(server.url.join("doc#usage").unwrap()))
+        .await
+        .unwrap();
+
+    assert_eq!(server.received().len(), 1);
+}
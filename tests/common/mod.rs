@@ -0,0 +1,203 @@
+//! A minimal HTTP/1.1 server answering each connection with a canned response.
+
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use spider_downloader::Clock;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub method: String,
+    pub target: String,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Received {
+    /// Returns the first value of header `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A running test server.
+pub struct Server {
+    pub url: Url,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl Server {
+    /// Returns the requests received so far, in order.
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+/// Builds a complete response closing the connection after `body`.
+pub fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut raw = format!("HTTP/1.1 {}\r\n", status);
+    for (name, value) in headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    let mut raw = raw.into_bytes();
+    raw.extend_from_slice(body);
+    raw
+}
+
+/// Builds a response sending `body` as a single chunk and closing the connection.
+///
+/// `transfer_encoding` is listed before the final `chunked`.
+pub fn chunked_response(
+    status: &str,
+    headers: &[(&str, &str)],
+    transfer_encoding: &str,
+    body: &[u8],
+) -> Vec<u8> {
+    let mut raw = format!("HTTP/1.1 {}\r\n", status);
+    for (name, value) in headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str(&format!(
+        "Transfer-Encoding: {}, chunked\r\nConnection: close\r\n\r\n{:x}\r\n",
+        transfer_encoding,
+        body.len()
+    ));
+    let mut raw = raw.into_bytes();
+    raw.extend_from_slice(body);
+    raw.extend_from_slice(b"\r\n0\r\n\r\n");
+    raw
+}
+
+/// Starts a server answering the n-th connection with `responses[n]`.
+///
+/// Once the list runs out the last response is repeated. Responses are sent
+/// as given, so they should close the connection (see [`response`]).
+pub async fn serve(responses: Vec<Vec<u8>>) -> Server {
+    assert!(!responses.is_empty());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+    tokio::spawn(async move {
+        let mut next = 0;
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                return;
+            };
+            let raw = responses[next.min(responses.len() - 1)].clone();
+            next += 1;
+            let log = log.clone();
+            tokio::spawn(answer(stream, raw, log));
+        }
+    });
+    Server { url, received }
+}
+
+/// Reads one request, records it and sends `raw`.
+async fn answer(mut stream: TcpStream, raw: Vec<u8>, log: Arc<Mutex<Vec<Received>>>) {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let mut chunk = [0; 4096];
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(n, v)| (n.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    let len: usize = headers
+        .iter()
+        .find(|(n, _)| n == "content-length")
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < len {
+        let mut chunk = [0; 4096];
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    // Recorded before answering, so it is visible once the client has the response.
+    log.lock().unwrap().push(Received {
+        method,
+        target,
+        headers,
+        body,
+    });
+    let _ = stream.write_all(&raw).await;
+    let _ = stream.shutdown().await;
+}
+
+/// A paused clock: time only moves when slept on or advanced, and sleeps return at once.
+#[derive(Debug)]
+pub struct PausedClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl PausedClock {
+    pub fn new() -> Self {
+        PausedClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+            sleeps: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Returns every duration slept on this clock, in order.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
+
+impl Default for PausedClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Clock for PausedClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.sleeps.lock().unwrap().push(duration);
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use common::{response, serve};
+use spider_downloader::{Downloader, Request, ReqwestClientDownloader};
+
+#[tokio::test]
+async fn url_credentials_become_basic_auth_and_leave_the_url() {
+    let server = serve(vec![response("200 OK", &[], b"secret")]).await;
+    let mut url = server.url.join("private").unwrap();
+    url.set_username("alice").unwrap();
+    url.set_password(Some("p@ss:word")).unwrap();
+
+    let response = ReqwestClientDownloader::new()
+        .download(Request::new(url))
+        .await
+        .unwrap();
+
+    let received = &server.received()[0];
+    let expected = format!("Basic {}", BASE64.encode("alice:p@ss:word"));
+    assert_eq!(received.header("authorization"), Some(expected.as_str()));
+    assert_eq!(received.target, "/private");
+    assert!(!received.header("host").unwrap().contains('@'));
+    assert_eq!(response.url.username(), "");
+    assert_eq!(response.url.password(), None);
+    assert!(!response.url.as_str().contains("alice"));
+}
+
+#[tokio::test]
+async fn explicit_authorization_header_wins_over_url_credentials() {
+    let server = serve(vec![response("200 OK", &[], b"")]).await;
+    let mut url = server.url.clone();
+    url.set_username("alice").unwrap();
+    url.set_password(Some("secret")).unwrap();
+    let mut request = Request::new(url);
+    request
+        .headers
+        .insert("authorization", "Bearer token".parse().unwrap());
+
+    let response = ReqwestClientDownloader::new()
+        .download(request)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.received()[0].header("authorization"),
+        Some("Bearer token")
+    );
+    assert_eq!(response.url.password(), None);
+}
@@ -0,0 +1,268 @@
+mod common;
+
+use bytes::BytesMut;
+use common::{chunked_response, response, serve};
+use flate2::Compression;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use http::Method;
+use spider_downloader::{Downloader, Request, ReqwestClientDownloader};
+use std::io::Write;
+
+#[tokio::test]
+async fn head_response_with_content_encoding_is_not_decoded() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        b"",
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+    let mut request = Request::new(server.url.clone());
+    request.method = Method::HEAD;
+
+    let response = downloader.download(request).await.unwrap();
+
+    assert!(response.body.is_empty());
+    assert_eq!(response.headers.get("content-encoding").unwrap(), "gzip");
+}
+
+#[tokio::test]
+async fn no_content_with_content_encoding_is_not_decoded() {
+    let server = serve(vec![response(
+        "204 No Content",
+        &[("Content-Encoding", "gzip")],
+        b"",
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status.as_u16(), 204);
+    assert!(response.body.is_empty());
+}
+
+#[tokio::test]
+async fn empty_encoded_body_is_not_decoded() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        b"",
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert!(response.body.is_empty());
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[tokio::test]
+async fn content_encoding_is_decoded() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        &gzip(b"hello"),
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello");
+    assert!(response.headers.get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn transfer_encoding_is_decoded() {
+    let server = serve(vec![chunked_response(
+        "200 OK",
+        &[],
+        "gzip",
+        &gzip(b"hello"),
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello");
+    assert!(response.headers.get("transfer-encoding").is_none());
+}
+
+#[tokio::test]
+async fn transfer_coding_is_removed_before_content_coding() {
+    // The sender deflated the content, then gzipped it for transfer.
+    let wire = gzip(&deflate(b"hello"));
+    let server = serve(vec![chunked_response(
+        "200 OK",
+        &[("Content-Encoding", "deflate")],
+        "gzip",
+        &wire,
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello");
+}
+
+#[tokio::test]
+async fn nested_decompression_bomb_is_stopped() {
+    // Small once the outer gzip is removed; the inner one inflates to 16 MiB.
+    let wire = gzip(&gzip(&vec![0; 16 * 1024 * 1024]));
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip, gzip")],
+        &wire,
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new()
+        .decompress(true)
+        .max_decompressed_bytes(64 * 1024);
+
+    let err = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("limit of 65536 bytes"), "{}", err);
+}
+
+#[cfg(feature = "brotli")]
+#[tokio::test]
+async fn brotli_round_trip() {
+    let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+    encoder.write_all(b"hello brotli").unwrap();
+    let encoded = encoder.into_inner();
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "br")],
+        &encoded,
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello brotli");
+    let accept = server.received()[0]
+        .header("accept-encoding")
+        .unwrap()
+        .to_string();
+    assert!(accept.split(", ").any(|coding| coding == "br"));
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn zstd_round_trip() {
+    let encoded = zstd::encode_all(&b"hello zstd"[..], 0).unwrap();
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "zstd")],
+        &encoded,
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello zstd");
+    let accept = server.received()[0]
+        .header("accept-encoding")
+        .unwrap()
+        .to_string();
+    assert!(accept.split(", ").any(|coding| coding == "zstd"));
+}
+
+#[tokio::test]
+async fn download_into_decodes_into_the_buffer() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        &gzip(b"hello"),
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+    let mut buf = BytesMut::new();
+
+    let response = downloader
+        .download_into(Request::new(server.url.clone()), &mut buf)
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello");
+    assert!(response.headers.get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn download_into_stops_a_decompression_bomb() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        &gzip(&vec![0; 1024 * 1024]),
+    )])
+    .await;
+    let downloader = ReqwestClientDownloader::new()
+        .decompress(true)
+        .max_decompressed_bytes(64 * 1024);
+    let mut buf = BytesMut::new();
+
+    let err = downloader
+        .download_into(Request::new(server.url.clone()), &mut buf)
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("limit of 65536 bytes"), "{}", err);
+}
+
+#[tokio::test]
+async fn head_bytes_ask_for_an_unencoded_body() {
+    let server = serve(vec![response("200 OK", &[], b"hello world")]).await;
+    let downloader = ReqwestClientDownloader::new().decompress(true);
+
+    let response = downloader
+        .download_head_bytes(Request::new(server.url.clone()), 5)
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"hello");
+    assert_eq!(
+        server.received()[0].header("accept-encoding"),
+        Some("identity")
+    );
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{response, serve};
+use md5::{Digest, Md5};
+use spider_downloader::{Downloader, Request, ReqwestClientDownloader};
+use std::collections::HashMap;
+
+/// Splits a `Digest` authorization header into its parameters.
+fn params(header: &str) -> HashMap<String, String> {
+    header
+        .strip_prefix("Digest ")
+        .unwrap()
+        .split(", ")
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap();
+            (name.to_string(), value.trim_matches('"').to_string())
+        })
+        .collect()
+}
+
+fn md5(data: &str) -> String {
+    format!("{:x}", Md5::digest(data.as_bytes()))
+}
+
+#[tokio::test]
+async fn digest_challenge_is_found_among_other_schemes() {
+    let challenge = "Basic realm=\"basic\", Digest realm=\"test@example.com\", \
+                     qop=\"auth,auth-int\", nonce=\"abc123\", opaque=\"xyz\", \
+                     Newauth realm=\"other\", type=1";
+    let server = serve(vec![
+        response("401 Unauthorized", &[("WWW-Authenticate", challenge)], b""),
+        response("200 OK", &[], b"welcome"),
+    ])
+    .await;
+    let downloader = ReqwestClientDownloader::new().with_digest_auth("alice", "secret");
+
+    let response = downloader
+        .download(Request::new(server.url.join("private").unwrap()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"welcome");
+    let received = server.received();
+    assert_eq!(received.len(), 2);
+    let sent = params(received[1].header("authorization").unwrap());
+    assert_eq!(sent["username"], "alice");
+    assert_eq!(sent["realm"], "test@example.com");
+    assert_eq!(sent["nonce"], "abc123");
+    assert_eq!(sent["opaque"], "xyz");
+    assert_eq!(sent["uri"], "/private");
+    assert_eq!(sent["algorithm"], "MD5");
+    assert_eq!(sent["qop"], "auth");
+    assert_eq!(sent["nc"], "00000001");
+    let ha1 = md5("alice:test@example.com:secret");
+    let ha2 = md5("GET:/private");
+    let expected = md5(&format!(
+        "{}:abc123:00000001:{}:auth:{}",
+        ha1, sent["cnonce"], ha2
+    ));
+    assert_eq!(sent["response"], expected);
+}
+
+#[tokio::test]
+async fn strongest_offered_algorithm_is_chosen() {
+    let server = serve(vec![
+        response(
+            "401 Unauthorized",
+            &[
+                (
+                    "WWW-Authenticate",
+                    "Digest realm=\"r\", nonce=\"n1\", algorithm=MD5",
+                ),
+                (
+                    "WWW-Authenticate",
+                    "Digest realm=\"r\", nonce=\"n2\", algorithm=SHA-256",
+                ),
+            ],
+            b"",
+        ),
+        response("200 OK", &[], b""),
+    ])
+    .await;
+    let downloader = ReqwestClientDownloader::new().with_digest_auth("alice", "secret");
+
+    downloader
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    let sent = params(server.received()[1].header("authorization").unwrap());
+    assert_eq!(sent["algorithm"], "SHA-256");
+    assert_eq!(sent["nonce"], "n2");
+}
+
+#[tokio::test]
+async fn nonce_count_increases_on_later_requests() {
+    let server = serve(vec![
+        response(
+            "401 Unauthorized",
+            &[(
+                "WWW-Authenticate",
+                "Digest realm=\"r\", nonce=\"n\", qop=\"auth\"",
+            )],
+            b"",
+        ),
+        response("200 OK", &[], b""),
+    ])
+    .await;
+    let downloader = ReqwestClientDownloader::new().with_digest_auth("alice", "secret");
+
+    for _ in 0..3 {
+        downloader
+            .download(Request::new(server.url.clone()))
+            .await
+            .unwrap();
+    }
+
+    let received = server.received();
+    // The challenge is answered once, then the remembered one is answered up front.
+    assert_eq!(received.len(), 4);
+    assert_eq!(received[0].header("authorization"), None);
+    let counts: Vec<String> = received[1..]
+        .iter()
+        .map(|r| params(r.header("authorization").unwrap())["nc"].clone())
+        .collect();
+    assert_eq!(counts, ["00000001", "00000002", "00000003"]);
+}
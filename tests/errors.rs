@@ -0,0 +1,83 @@
+use spider_downloader::{
+    AttemptErrorKind, DownloadError, DownloadFailure, FixtureStore, Jitter, Request,
+    ReqwestClientDownloader, RetryPolicy,
+};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Returns a local URL nothing listens on.
+async fn refused_url() -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    Url::parse(&format!("http://{}/", addr)).unwrap()
+}
+
+#[tokio::test]
+async fn retries_exhausted_keeps_every_attempt() {
+    let downloader = ReqwestClientDownloader::new()
+        .retry_policy(RetryPolicy::new(2, Duration::from_millis(1)).with_jitter(Jitter::None));
+
+    let err = downloader
+        .try_download(Request::new(refused_url().await))
+        .await
+        .unwrap_err();
+
+    match err {
+        DownloadFailure::Download(DownloadError::RetriesExhausted { attempts }) => {
+            let numbers: Vec<u32> = attempts.iter().map(|a| a.attempt).collect();
+            assert_eq!(numbers, [1, 2, 3]);
+            assert!(attempts.iter().all(|a| a.kind == AttemptErrorKind::Connect));
+            assert_eq!(attempts[0].delay, Duration::ZERO);
+            assert_eq!(attempts[1].delay, Duration::from_millis(1));
+            assert_eq!(attempts[2].delay, Duration::from_millis(2));
+        }
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+#[tokio::test]
+async fn attempts_accessor_matches_the_variant() {
+    let downloader = ReqwestClientDownloader::new()
+        .retry_policy(RetryPolicy::new(1, Duration::from_millis(1)).with_jitter(Jitter::None));
+
+    let err = downloader
+        .try_download(Request::new(refused_url().await))
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.attempts().map(<[_]>::len), Some(2));
+    assert!(matches!(
+        err.download_error(),
+        Some(DownloadError::RetriesExhausted { .. })
+    ));
+}
+
+#[tokio::test]
+async fn passed_deadline_is_reported_as_such() {
+    let downloader = ReqwestClientDownloader::new().global_deadline(Instant::now());
+
+    let err = downloader
+        .try_download(Request::new(refused_url().await))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        DownloadFailure::Download(DownloadError::DeadlineExceeded)
+    ));
+}
+
+#[tokio::test]
+async fn missing_recording_is_reported_with_its_fingerprint() {
+    let dir = std::env::temp_dir().join(format!("spider-downloader-{}", uuid::Uuid::new_v4()));
+    let store = FixtureStore::new(dir);
+    let request = Request::new(Url::parse("http://example.com/").unwrap());
+
+    let err = store.load("missing", request).await.unwrap_err();
+
+    assert!(matches!(
+        err,
+        DownloadError::NoRecording { fingerprint } if fingerprint == "missing"
+    ));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use bytes::Bytes;
+use common::{response, serve};
+use http::header::CONTENT_LENGTH;
+use http::{HeaderValue, Method};
+use serde_json::json;
+use spider_downloader::{Body, BodyExt, Downloader, Request, RequestExt, ReqwestClientDownloader};
+
+#[tokio::test]
+async fn delete_sends_a_json_body() {
+    let server = serve(vec![response("200 OK", &[], b"deleted")]).await;
+    let mut request = Request::new(server.url.join("items/7").unwrap());
+    request.method = Method::DELETE;
+    request.body = Some(Body::Json(json!({ "reason": "obsolete" })));
+
+    let response = ReqwestClientDownloader::new()
+        .download(request)
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"deleted");
+    let received = &server.received()[0];
+    assert_eq!(received.method, "DELETE");
+    assert_eq!(received.target, "/items/7");
+    assert_eq!(received.header("content-type"), Some("application/json"));
+    let sent: serde_json::Value = serde_json::from_slice(&received.body).unwrap();
+    assert_eq!(sent, json!({ "reason": "obsolete" }));
+}
+
+#[tokio::test]
+async fn patch_sends_form_data() {
+    let server = serve(vec![response("200 OK", &[], b"patched")]).await;
+    let mut request = Request::new(server.url.join("items/7").unwrap());
+    request.method = Method::PATCH;
+    request.body = Some(Body::form_from(&[("name", "new name")]).unwrap());
+
+    let response = ReqwestClientDownloader::new()
+        .download(request)
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"patched");
+    let received = &server.received()[0];
+    assert_eq!(received.method, "PATCH");
+    assert_eq!(
+        received.header("content-type"),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(received.body, b"name=new+name");
+}
+
+#[tokio::test]
+async fn expectation_failed_is_retried_without_expect() {
+    let server = serve(vec![
+        response("417 Expectation Failed", &[], b""),
+        response("200 OK", &[], b"stored"),
+    ])
+    .await;
+    let mut request = Request::new(server.url.clone());
+    request.method = Method::POST;
+    request.body = Some(Body::Bytes(Bytes::from_static(b"payload")));
+
+    let response = ReqwestClientDownloader::new()
+        .expect_continue(true)
+        .download(request)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status.as_u16(), 200);
+    assert_eq!(&response.body[..], b"stored");
+    let received = server.received();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].header("expect"), Some("100-continue"));
+    assert_eq!(received[1].header("expect"), None);
+    assert_eq!(received[1].body, b"payload");
+}
+
+#[tokio::test]
+async fn empty_body_is_sent_with_content_length_zero() {
+    let server = serve(vec![response("200 OK", &[], b"")]).await;
+    let mut request = Request::new(server.url.clone()).with_empty_body();
+    request.method = Method::POST;
+
+    ReqwestClientDownloader::new()
+        .download(request)
+        .await
+        .unwrap();
+
+    let received = &server.received()[0];
+    assert_eq!(received.header("content-length"), Some("0"));
+    assert!(received.body.is_empty());
+}
+
+#[tokio::test]
+async fn no_body_is_sent_without_content_length() {
+    let server = serve(vec![response("200 OK", &[], b"")]).await;
+
+    ReqwestClientDownloader::new()
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    let received = &server.received()[0];
+    assert_eq!(received.header("content-length"), None);
+    assert_eq!(received.header("transfer-encoding"), None);
+}
+
+#[tokio::test]
+async fn caller_content_length_is_not_duplicated() {
+    let server = serve(vec![response("200 OK", &[], b"")]).await;
+    let mut request = Request::new(server.url.clone()).with_empty_body();
+    request.method = Method::PUT;
+    request
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
+
+    ReqwestClientDownloader::new()
+        .download(request)
+        .await
+        .unwrap();
+
+    let received = &server.received()[0];
+    let lengths: Vec<_> = received
+        .headers
+        .iter()
+        .filter(|(name, _)| name == "content-length")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(lengths, ["0"]);
+}
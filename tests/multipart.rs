@@ -0,0 +1,154 @@
+use bytes::Bytes;
+use futures_util::{StreamExt, stream};
+use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use spider_downloader::{MultipartPart, StreamingResponse};
+use spider_util::error::SpiderError;
+use url::Url;
+
+/// Returns a `content_type` response whose body arrives in chunks of `chunk_len` bytes.
+fn streaming(content_type: &str, body: &[u8], chunk_len: usize) -> StreamingResponse {
+    let url = Url::parse("http://example.com/stream").unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type).unwrap());
+    let chunks: Vec<_> = body
+        .chunks(chunk_len)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect();
+    StreamingResponse {
+        url: url.clone(),
+        status: StatusCode::OK,
+        headers,
+        request_url: url,
+        body: Box::pin(stream::iter(chunks)),
+    }
+}
+
+async fn parts(
+    content_type: &str,
+    body: &[u8],
+    chunk_len: usize,
+) -> Vec<Result<MultipartPart, SpiderError>> {
+    streaming(content_type, body, chunk_len)
+        .multipart()
+        .unwrap()
+        .collect()
+        .await
+}
+
+fn bodies(parts: &[Result<MultipartPart, SpiderError>]) -> Vec<&[u8]> {
+    parts
+        .iter()
+        .map(|part| part.as_ref().unwrap().body.as_ref())
+        .collect()
+}
+
+#[tokio::test]
+async fn quoted_boundary_with_special_characters_is_used() {
+    let body = b"preamble\r\n\
+        --a+b.c?(d)\r\nContent-Type: text/plain\r\n\r\nfirst\r\n\
+        --a+b.c?(d)\r\n\r\nsecond\r\n\
+        --a+b.c?(d)--\r\nepilogue";
+
+    for chunk_len in [1, 7, body.len()] {
+        let parts = parts(
+            "multipart/x-mixed-replace; charset=utf-8; BOUNDARY=\"a+b.c?(d)\"",
+            body,
+            chunk_len,
+        )
+        .await;
+
+        assert_eq!(bodies(&parts), [&b"first"[..], b"second"], "{}", chunk_len);
+        let first = parts[0].as_ref().unwrap();
+        assert_eq!(first.headers[CONTENT_TYPE], "text/plain");
+    }
+}
+
+#[tokio::test]
+async fn non_multipart_or_boundaryless_types_are_rejected() {
+    for content_type in [
+        "text/html; boundary=frame",
+        "multipart/mixed",
+        "multipart/mixed; boundary=\"\"",
+    ] {
+        let err = streaming(content_type, b"", 1).multipart().err().unwrap();
+        assert!(err.to_string().contains("boundary"), "{}", err);
+    }
+}
+
+#[tokio::test]
+async fn part_header_values_are_kept_verbatim() {
+    let body = b"--frame\r\n\
+        Content-Disposition: form-data; name=\"a\\\"b\"; filename=\"x;y.txt\"\r\n\
+        not a header line\r\n\
+        \r\n\
+        value\r\n\
+        --frame--\r\n";
+
+    let parts = parts("multipart/form-data; boundary=frame", body, 5).await;
+
+    assert_eq!(bodies(&parts), [&b"value"[..]]);
+    let part = parts[0].as_ref().unwrap();
+    assert_eq!(
+        part.headers[CONTENT_DISPOSITION],
+        "form-data; name=\"a\\\"b\"; filename=\"x;y.txt\""
+    );
+    assert_eq!(part.headers.len(), 1);
+}
+
+#[tokio::test]
+async fn content_length_part_may_contain_the_delimiter() {
+    let image = b"A\r\n--frame\r\nB";
+    let mut body = format!(
+        "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        image.len()
+    )
+    .into_bytes();
+    body.extend_from_slice(image);
+    body.extend_from_slice(b"\r\n--frame\r\n\r\nnext\r\n--frame--\r\n");
+
+    for chunk_len in [1, 4, body.len()] {
+        let parts = parts(
+            "multipart/x-mixed-replace; boundary=frame",
+            &body,
+            chunk_len,
+        )
+        .await;
+
+        assert_eq!(bodies(&parts), [&image[..], b"next"], "{}", chunk_len);
+    }
+}
+
+#[tokio::test]
+async fn delimiter_inside_a_line_does_not_end_the_part() {
+    let body = b"--frame\r\n\r\nx--frame y\r\n--frame--";
+
+    let parts = parts("multipart/mixed; boundary=frame", body, 3).await;
+
+    assert_eq!(bodies(&parts), [&b"x--frame y"[..]]);
+}
+
+#[tokio::test]
+async fn bare_line_feeds_and_missing_close_delimiter_are_tolerated() {
+    let body = b"--frame\nContent-Type: text/plain\n\nx\n--frame\n\ny\n--frame\n";
+
+    let parts = parts("multipart/x-mixed-replace; boundary=frame", body, 2).await;
+
+    assert_eq!(bodies(&parts), [&b"x"[..], b"y"]);
+    assert_eq!(
+        parts[0].as_ref().unwrap().headers[CONTENT_TYPE],
+        "text/plain"
+    );
+}
+
+#[tokio::test]
+async fn body_ending_inside_a_part_is_an_error() {
+    let body = b"--frame\r\n\r\nwhole\r\n--frame\r\nContent-Length: 10\r\n\r\nabc";
+
+    let parts = parts("multipart/x-mixed-replace; boundary=frame", body, 4).await;
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].as_ref().unwrap().body, "whole");
+    let err = parts[1].as_ref().unwrap_err().to_string();
+    assert!(err.contains("ended inside a part"), "{}", err);
+}
@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use futures_util::{StreamExt, stream};
+use http::{HeaderMap, StatusCode};
+use serde_json::{Value, json};
+use spider_downloader::StreamingResponse;
+use url::Url;
+
+/// Returns a response whose body arrives as `chunks`.
+fn streaming(chunks: &[&'static [u8]]) -> StreamingResponse {
+    let url = Url::parse("http://example.com/feed").unwrap();
+    let chunks: Vec<_> = chunks
+        .iter()
+        .map(|chunk| Ok(Bytes::from_static(chunk)))
+        .collect();
+    StreamingResponse {
+        url: url.clone(),
+        status: StatusCode::OK,
+        headers: HeaderMap::new(),
+        request_url: url,
+        body: Box::pin(stream::iter(chunks)),
+    }
+}
+
+async fn values(chunks: &[&'static [u8]]) -> Vec<Value> {
+    streaming(chunks)
+        .ndjson()
+        .map(|value| value.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn lines_split_across_chunks_are_joined() {
+    let values = values(&[b"{\"a\":", b"1}\n{\"b\"", b":2}\n"]).await;
+
+    assert_eq!(values, [json!({ "a": 1 }), json!({ "b": 2 })]);
+}
+
+#[tokio::test]
+async fn trailing_line_without_newline_is_parsed() {
+    let values = values(&[b"{\"a\":1}\n\n  \n{\"b\":2}"]).await;
+
+    assert_eq!(values, [json!({ "a": 1 }), json!({ "b": 2 })]);
+}
+
+#[tokio::test]
+async fn malformed_line_is_an_error_and_parsing_continues() {
+    let results: Vec<_> = streaming(&[b"{\"a\":1}\nnot json\n{\"b\":2}\n"])
+        .ndjson()
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &json!({ "a": 1 }));
+    let err = results[1].as_ref().unwrap_err().to_string();
+    assert!(err.contains("not json"), "{}", err);
+    assert_eq!(results[2].as_ref().unwrap(), &json!({ "b": 2 }));
+}
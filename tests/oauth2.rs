@@ -0,0 +1,176 @@
+mod common;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use common::PausedClock;
+use futures_util::future::join_all;
+use http::{HeaderMap, StatusCode};
+use spider_downloader::{
+    DownloadError, Downloader, OAuth2Provider, Request, ReqwestClientDownloader, SimpleHttpClient,
+};
+use spider_util::error::SpiderError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
+
+const TOKEN_URL: &str = "https://auth.example.com/token";
+
+/// A token endpoint issuing `token-<n>` on its n-th call.
+struct TokenEndpoint {
+    status: StatusCode,
+    expires_in: Option<u64>,
+    calls: AtomicUsize,
+    authorization: Mutex<Option<String>>,
+}
+
+impl TokenEndpoint {
+    fn new(expires_in: Option<u64>) -> Arc<Self> {
+        Arc::new(TokenEndpoint {
+            status: StatusCode::OK,
+            expires_in,
+            calls: AtomicUsize::new(0),
+            authorization: Mutex::new(None),
+        })
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl SimpleHttpClient for TokenEndpoint {
+    async fn get_text(
+        &self,
+        url: &str,
+        _timeout: Duration,
+    ) -> Result<(StatusCode, Bytes), SpiderError> {
+        panic!("unexpected GET {}", url);
+    }
+
+    async fn post_form(
+        &self,
+        _url: &str,
+        _form: &[(&str, &str)],
+        headers: HeaderMap,
+        _timeout: Duration,
+    ) -> Result<(StatusCode, Bytes), SpiderError> {
+        let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.authorization.lock().unwrap() = headers
+            .get("authorization")
+            .map(|v| v.to_str().unwrap().to_string());
+        // Gives concurrent callers the chance to pile up behind this refresh.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let body = serde_json::json!({
+            "access_token": format!("token-{}", n),
+            "token_type": "Bearer",
+            "expires_in": self.expires_in,
+        });
+        Ok((self.status, Bytes::from(body.to_string())))
+    }
+}
+
+fn provider(endpoint: &Arc<TokenEndpoint>) -> OAuth2Provider {
+    OAuth2Provider::new(endpoint.clone(), TOKEN_URL, "client", "secret")
+}
+
+#[tokio::test]
+async fn concurrent_callers_share_one_refresh() {
+    let endpoint = TokenEndpoint::new(Some(3600));
+    let provider = provider(&endpoint);
+
+    let tokens = join_all((0..10).map(|_| provider.authorization())).await;
+
+    assert_eq!(endpoint.calls(), 1);
+    assert!(
+        tokens
+            .iter()
+            .all(|t| t.as_ref().unwrap() == "Bearer token-1")
+    );
+}
+
+#[tokio::test]
+async fn rejected_token_is_replaced() {
+    let endpoint = TokenEndpoint::new(Some(3600));
+    let provider = provider(&endpoint);
+
+    let rejected = provider.authorization().await.unwrap();
+    provider.invalidate(&rejected);
+    let replacement = provider.authorization().await.unwrap();
+    // A late rejection of the old token leaves the new one alone.
+    provider.invalidate(&rejected);
+
+    assert_eq!(replacement, "Bearer token-2");
+    assert_eq!(provider.authorization().await.unwrap(), "Bearer token-2");
+    assert_eq!(endpoint.calls(), 2);
+}
+
+#[tokio::test]
+async fn lifetime_within_the_margin_is_refreshed_halfway() {
+    for lifetime in [30, 60] {
+        let endpoint = TokenEndpoint::new(Some(lifetime));
+        let clock = Arc::new(PausedClock::new());
+        let provider = provider(&endpoint)
+            .refresh_margin(Duration::from_secs(60))
+            .clock(clock.clone());
+
+        provider.authorization().await.unwrap();
+        provider.authorization().await.unwrap();
+        clock.advance(Duration::from_secs(lifetime / 2 - 1));
+        provider.authorization().await.unwrap();
+        assert_eq!(endpoint.calls(), 1, "lifetime {}s", lifetime);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(provider.authorization().await.unwrap(), "Bearer token-2");
+        assert_eq!(endpoint.calls(), 2, "lifetime {}s", lifetime);
+    }
+}
+
+#[tokio::test]
+async fn client_credentials_are_form_urlencoded() {
+    let endpoint = TokenEndpoint::new(None);
+    let provider = OAuth2Provider::new(endpoint.clone(), TOKEN_URL, "my client", "p@ss:word");
+
+    provider.authorization().await.unwrap();
+
+    let expected = format!("Basic {}", BASE64.encode("my+client:p%40ss%3Aword"));
+    assert_eq!(
+        endpoint.authorization.lock().unwrap().as_deref(),
+        Some(expected.as_str())
+    );
+}
+
+#[tokio::test]
+async fn failed_token_request_is_structured() {
+    let endpoint = Arc::new(TokenEndpoint {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        expires_in: None,
+        calls: AtomicUsize::new(0),
+        authorization: Mutex::new(None),
+    });
+
+    let err = provider(&endpoint).authorization().await.unwrap_err();
+
+    assert!(matches!(
+        err,
+        DownloadError::TokenRequestFailed { url, .. } if url == TOKEN_URL
+    ));
+}
+
+#[tokio::test]
+async fn dry_run_does_not_fetch_a_token() {
+    let endpoint = TokenEndpoint::new(Some(3600));
+    let downloader = ReqwestClientDownloader::new()
+        .oauth2(Arc::new(provider(&endpoint)))
+        .dry_run(true);
+
+    downloader
+        .download(Request::new(Url::parse("https://example.com/").unwrap()))
+        .await
+        .unwrap();
+
+    assert_eq!(endpoint.calls(), 0);
+}
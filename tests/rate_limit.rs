@@ -0,0 +1,103 @@
+mod common;
+
+use common::{response, serve};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::json;
+use spider_downloader::{
+    Downloader, META_RATE_LIMIT, RateLimitInfo, Request, ReqwestClientDownloader,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        );
+    }
+    headers
+}
+
+#[test]
+fn header_families_are_normalized() {
+    for prefix in ["X-RateLimit-", "X-Rate-Limit-", "RateLimit-"] {
+        let received = headers(&[
+            (format!("{prefix}Limit").as_str(), "100"),
+            (format!("{prefix}Remaining").as_str(), "7"),
+            (format!("{prefix}Reset").as_str(), "30"),
+        ]);
+
+        assert_eq!(
+            RateLimitInfo::from_headers(&received),
+            Some(RateLimitInfo {
+                retry_after: None,
+                limit: Some(100),
+                remaining: Some(7),
+                reset_after: Some(Duration::from_secs(30)),
+            }),
+            "{}",
+            prefix
+        );
+    }
+}
+
+#[test]
+fn fractional_and_policy_values_are_read() {
+    let info = RateLimitInfo::from_headers(&headers(&[
+        ("ratelimit-limit", "100;w=60"),
+        ("ratelimit-reset", "1.2"),
+    ]))
+    .unwrap();
+
+    assert_eq!(info.limit, Some(100));
+    assert_eq!(info.reset_after, Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn reset_timestamp_becomes_a_delay() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let info = RateLimitInfo::from_headers(&headers(&[(
+        "x-ratelimit-reset",
+        (now + 60).to_string().as_str(),
+    )]))
+    .unwrap();
+
+    let delay = info.reset_after.unwrap();
+    assert!(
+        delay <= Duration::from_secs(60) && delay >= Duration::from_secs(58),
+        "{:?}",
+        delay
+    );
+}
+
+#[test]
+fn no_signals_is_none() {
+    assert_eq!(
+        RateLimitInfo::from_headers(&headers(&[("content-type", "text/html")])),
+        None
+    );
+}
+
+#[tokio::test]
+async fn signals_are_reported_in_meta() {
+    let server = serve(vec![response(
+        "429 Too Many Requests",
+        &[("Retry-After", "5"), ("X-RateLimit-Remaining", "0")],
+        b"",
+    )])
+    .await;
+
+    let response = ReqwestClientDownloader::new()
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.meta.get(META_RATE_LIMIT).map(|v| v.clone()),
+        Some(json!({ "retry_after_ms": 5000, "remaining": 0 }))
+    );
+}
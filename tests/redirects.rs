@@ -0,0 +1,79 @@
+mod common;
+
+use common::{response, serve};
+use http::HeaderValue;
+use http::header::{AUTHORIZATION, COOKIE};
+use spider_downloader::{Downloader, META_MALFORMED_REDIRECT, Request, ReqwestClientDownloader};
+
+#[tokio::test]
+async fn redirect_without_location_is_flagged() {
+    let server = serve(vec![response("302 Found", &[], b"")]).await;
+
+    let response = ReqwestClientDownloader::new()
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status.as_u16(), 302);
+    assert_eq!(
+        response
+            .meta
+            .get(META_MALFORMED_REDIRECT)
+            .and_then(|v| v.as_bool()),
+        Some(true)
+    );
+}
+
+#[tokio::test]
+async fn followed_redirect_is_not_flagged() {
+    let server = serve(vec![
+        response("302 Found", &[("Location", "/final")], b""),
+        response("200 OK", &[], b"done"),
+    ])
+    .await;
+
+    let response = ReqwestClientDownloader::new()
+        .download(Request::new(server.url.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"done");
+    assert!(response.meta.get(META_MALFORMED_REDIRECT).is_none());
+}
+
+#[tokio::test]
+async fn meta_refresh_to_another_origin_drops_credentials() {
+    let other = serve(vec![response("200 OK", &[], b"landed")]).await;
+    let page = format!(
+        "<html><head><meta http-equiv=\"refresh\" content=\"0; url={}landing\"></head></html>",
+        other.url
+    );
+    let origin = serve(vec![response(
+        "200 OK",
+        &[("Content-Type", "text/html")],
+        page.as_bytes(),
+    )])
+    .await;
+    let mut url = origin.url.clone();
+    url.set_username("user").unwrap();
+    url.set_password(Some("secret")).unwrap();
+    let mut request = Request::new(url);
+    request
+        .headers
+        .insert(COOKIE, HeaderValue::from_static("session=1"));
+
+    let response = ReqwestClientDownloader::new()
+        .follow_meta_refresh(true)
+        .download(request)
+        .await
+        .unwrap();
+
+    assert_eq!(&response.body[..], b"landed");
+    let first = &origin.received()[0];
+    assert!(first.header(AUTHORIZATION.as_str()).is_some());
+    assert_eq!(first.header(COOKIE.as_str()), Some("session=1"));
+    let hop = &other.received()[0];
+    assert_eq!(hop.target, "/landing");
+    assert_eq!(hop.header(AUTHORIZATION.as_str()), None);
+    assert_eq!(hop.header(COOKIE.as_str()), None);
+}
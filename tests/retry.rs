@@ -0,0 +1,114 @@
+mod common;
+
+use common::{PausedClock, response, serve};
+use spider_downloader::{
+    AttemptError, AttemptErrorKind, DownloadError, DownloadFailure, Jitter, Request,
+    ReqwestClientDownloader, RetryAction, RetryPolicy,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Downloads until retries run out and returns the recorded attempts and the sleeps made.
+async fn exhaust(
+    responses: Vec<Vec<u8>>,
+    policy: RetryPolicy,
+) -> (Vec<AttemptError>, Vec<Duration>) {
+    let server = serve(responses).await;
+    let clock = Arc::new(PausedClock::new());
+    let downloader = ReqwestClientDownloader::new()
+        .clock(clock.clone())
+        .retry_policy(policy.with_jitter(Jitter::None));
+
+    let err = downloader
+        .try_download(Request::new(server.url.clone()))
+        .await
+        .unwrap_err();
+
+    let DownloadFailure::Download(DownloadError::RetriesExhausted { attempts }) = err else {
+        panic!("expected RetriesExhausted, got {:?}", err);
+    };
+    assert_eq!(attempts.len(), server.received().len());
+    (attempts, clock.sleeps())
+}
+
+/// Downloads from a server that always answers `503` and returns the sleeps and attempts made.
+async fn run(policy: RetryPolicy) -> (Vec<Duration>, usize) {
+    let (attempts, sleeps) =
+        exhaust(vec![response("503 Service Unavailable", &[], b"")], policy).await;
+    assert!(
+        attempts
+            .iter()
+            .all(|attempt| attempt.kind == AttemptErrorKind::Status)
+    );
+    (sleeps, attempts.len())
+}
+
+#[tokio::test]
+async fn exhausted_retries_keep_transport_errors_and_statuses() {
+    // The first connection is closed without an answer.
+    let (attempts, _) = exhaust(
+        vec![Vec::new(), response("503 Service Unavailable", &[], b"")],
+        RetryPolicy::new(2, SECOND),
+    )
+    .await;
+
+    let kinds: Vec<AttemptErrorKind> = attempts.iter().map(|attempt| attempt.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            AttemptErrorKind::Other,
+            AttemptErrorKind::Status,
+            AttemptErrorKind::Status
+        ]
+    );
+    assert_eq!(attempts[2].message, "status 503 Service Unavailable");
+}
+
+#[tokio::test]
+async fn max_backoff_caps_each_delay() {
+    let (sleeps, attempts) = run(RetryPolicy::new(4, SECOND).with_max_backoff(3 * SECOND)).await;
+
+    assert_eq!(sleeps, [SECOND, 2 * SECOND, 3 * SECOND, 3 * SECOND]);
+    assert_eq!(attempts, 5);
+}
+
+#[tokio::test]
+async fn max_total_retry_time_stops_before_the_budget_is_exceeded() {
+    let (sleeps, attempts) =
+        run(RetryPolicy::new(10, SECOND).with_max_total_retry_time(5 * SECOND)).await;
+
+    // A third wait of 4s would bring the total to 7s.
+    assert_eq!(sleeps, [SECOND, 2 * SECOND]);
+    assert_eq!(attempts, 3);
+}
+
+#[tokio::test]
+async fn capped_delays_fill_the_budget() {
+    let (sleeps, attempts) = run(RetryPolicy::new(10, SECOND)
+        .with_max_backoff(2 * SECOND)
+        .with_max_total_retry_time(5 * SECOND))
+    .await;
+
+    assert_eq!(sleeps, [SECOND, 2 * SECOND, 2 * SECOND]);
+    assert_eq!(attempts, 4);
+}
+
+#[tokio::test]
+async fn max_backoff_caps_retry_after() {
+    let (attempts, sleeps) = exhaust(
+        vec![response(
+            "503 Service Unavailable",
+            &[("Retry-After", "120")],
+            b"",
+        )],
+        RetryPolicy::new(1, SECOND)
+            .retry_status(503, RetryAction::RetryAfter)
+            .with_max_backoff(3 * SECOND),
+    )
+    .await;
+
+    assert_eq!(sleeps, [3 * SECOND]);
+    assert_eq!(attempts.len(), 2);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::{response, serve};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use spider_downloader::SimpleHttpClient;
+use std::io::Write;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn get_text_decodes_gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"User-agent: *\nDisallow:\n").unwrap();
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        &encoder.finish().unwrap(),
+    )])
+    .await;
+
+    let (status, body) = reqwest::Client::new()
+        .get_text(server.url.join("robots.txt").unwrap().as_str(), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(&body[..], b"User-agent: *\nDisallow:\n");
+    let accept = server.received()[0]
+        .header("accept-encoding")
+        .unwrap()
+        .to_string();
+    assert!(accept.split(", ").any(|coding| coding == "gzip"));
+}
+
+#[tokio::test]
+async fn get_text_returns_an_unencoded_body_as_is() {
+    let server = serve(vec![response("200 OK", &[], b"plain")]).await;
+
+    let (_, body) = reqwest::Client::new()
+        .get_text(server.url.as_str(), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(&body[..], b"plain");
+}
+
+#[tokio::test]
+async fn get_text_keeps_an_empty_gzip_body() {
+    let server = serve(vec![response(
+        "200 OK",
+        &[("Content-Encoding", "gzip")],
+        b"",
+    )])
+    .await;
+
+    let (_, body) = reqwest::Client::new()
+        .get_text(server.url.as_str(), TIMEOUT)
+        .await
+        .unwrap();
+
+    assert!(body.is_empty());
+}
@@ -0,0 +1,76 @@
+use spider_downloader::normalize_url;
+use url::Url;
+
+fn normalized(url: &str, sort_query: bool) -> String {
+    let mut url = Url::parse(url).unwrap();
+    normalize_url(&mut url, sort_query);
+    url.to_string()
+}
+
+#[test]
+fn query_is_sorted_by_key_only_when_asked() {
+    assert_eq!(
+        normalized("http://example.com/?b=2&a=1&b=1", true),
+        "http://example.com/?a=1&b=2&b=1"
+    );
+    assert_eq!(
+        normalized("http://example.com/?b=2&a=1", false),
+        "http://example.com/?b=2&a=1"
+    );
+}
+
+#[test]
+fn default_ports_are_removed() {
+    assert_eq!(
+        normalized("https://example.com:443/a", false),
+        "https://example.com/a"
+    );
+    assert_eq!(
+        normalized("http://example.com:8080/a", false),
+        "http://example.com:8080/a"
+    );
+    assert_eq!(
+        normalized("ftp://example.com:21/a", false),
+        "ftp://example.com/a"
+    );
+}
+
+#[test]
+fn percent_escapes_are_uppercased() {
+    assert_eq!(
+        normalized("http://example.com/a%2fb?q=%c3%a9", false),
+        "http://example.com/a%2Fb?q=%C3%A9"
+    );
+    assert_eq!(
+        normalized("http://example.com/100%/x", false),
+        "http://example.com/100%/x"
+    );
+}
+
+#[test]
+fn fragment_is_removed() {
+    assert_eq!(
+        normalized("http://example.com/doc?x=1#intro", false),
+        "http://example.com/doc?x=1"
+    );
+}
+
+#[test]
+fn host_is_lowercased_and_slashes_collapsed() {
+    assert_eq!(
+        normalized("http://EXAMPLE.com//a///b", false),
+        "http://example.com/a/b"
+    );
+}
+
+#[test]
+fn trailing_slash_is_kept() {
+    assert_eq!(
+        normalized("http://example.com/a/", false),
+        "http://example.com/a/"
+    );
+    assert_eq!(
+        normalized("http://example.com/a", false),
+        "http://example.com/a"
+    );
+}